@@ -41,6 +41,9 @@
             # Python with pip for PyPI downloads
             (pkgs.python312.withPackages (ps: [ ps.pip ]))
 
+            # git for git:/git+https:// sources
+            pkgs.git
+
             # Development tools
             pkgs.cargo-watch
           ];
@@ -63,14 +66,16 @@
             pkgs.makeWrapper
           ];
 
+          # Tests read from throwaway git repositories
+          nativeCheckInputs = [ pkgs.git pkgs.python312 ];
+
+          # Fetching is done in-process; Python is only needed to parse
+          # Python sources and git to read git repositories
           postFixup = ''
             wrapProgram $out/bin/pkgctx \
               --prefix PATH : ${pkgs.lib.makeBinPath [
-                rWithPackages
-                (pkgs.python312.withPackages (ps: [ ps.pip ]))
-                pkgs.curl
-                pkgs.gnutar
-                pkgs.gzip
+                pkgs.python312
+                pkgs.git
               ]}
           '';
 
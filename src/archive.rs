@@ -0,0 +1,261 @@
+//! Archive extraction
+//!
+//! Unpacks source tarballs (`.tar.gz`, `.tar.bz2`, `.tar`) and zip files
+//! (including wheels) in-process, reporting corrupt archives as errors.
+//! Archives with members or links that would land outside the extraction
+//! directory are rejected.
+
+use crate::error::FetchError;
+use anyhow::{Context, Result};
+use std::fs::{self, File};
+use std::io::{self, BufReader, Read};
+use std::path::{Component, Path};
+
+/// Supported archive formats
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Format {
+    TarGz,
+    TarBz2,
+    Tar,
+    Zip,
+}
+
+/// Extract an archive into `dest_dir`.
+///
+/// The format is taken from the file name and, failing that, sniffed from
+/// the first bytes of the file.
+pub fn extract(archive_path: &Path, dest_dir: &Path) -> Result<()> {
+    extract_filtered(archive_path, dest_dir, |_| true)
+}
+
+/// Extract only the archive members whose relative path satisfies `keep`
+pub fn extract_filtered(
+    archive_path: &Path,
+    dest_dir: &Path,
+    keep: impl Fn(&Path) -> bool,
+) -> Result<()> {
+    let file = File::open(archive_path)
+        .with_context(|| format!("Failed to open archive {}", archive_path.display()))?;
+    let reader = BufReader::new(file);
+
+    let result = detect_format(archive_path).and_then(|format| match format {
+        Format::TarGz => unpack_tar(flate2::read::GzDecoder::new(reader), dest_dir, &keep),
+        Format::TarBz2 => unpack_tar(bzip2::read::BzDecoder::new(reader), dest_dir, &keep),
+        Format::Tar => unpack_tar(reader, dest_dir, &keep),
+        Format::Zip => unpack_zip(archive_path, dest_dir, &keep),
+    });
+
+    result.map_err(|e| {
+        let file_name = archive_path.file_name().unwrap_or(archive_path.as_os_str());
+        FetchError::CorruptArchive {
+            file: file_name.to_string_lossy().into_owned(),
+            reason: format!("{e:#}"),
+        }
+        .into()
+    })
+}
+
+/// Whether a file name looks like an archive this module can extract
+pub fn is_archive_name(file_name: &str) -> bool {
+    format_from_name(file_name).is_some()
+}
+
+fn format_from_name(file_name: &str) -> Option<Format> {
+    let name = file_name.to_ascii_lowercase();
+    if name.ends_with(".tar.gz") || name.ends_with(".tgz") {
+        Some(Format::TarGz)
+    } else if name.ends_with(".tar.bz2") || name.ends_with(".tbz2") {
+        Some(Format::TarBz2)
+    } else if name.ends_with(".tar") {
+        Some(Format::Tar)
+    } else if name.ends_with(".zip") || name.ends_with(".whl") {
+        Some(Format::Zip)
+    } else {
+        None
+    }
+}
+
+fn detect_format(archive_path: &Path) -> Result<Format> {
+    let file_name = archive_path
+        .file_name()
+        .and_then(|n| n.to_str())
+        .unwrap_or("");
+    if let Some(format) = format_from_name(file_name) {
+        return Ok(format);
+    }
+
+    let mut magic = [0u8; 4];
+    let read = File::open(archive_path)
+        .and_then(|mut f| f.read(&mut magic))
+        .with_context(|| format!("Failed to read archive {}", archive_path.display()))?;
+
+    match &magic[..read] {
+        [0x1f, 0x8b, ..] => Ok(Format::TarGz),
+        [b'B', b'Z', b'h', ..] => Ok(Format::TarBz2),
+        [b'P', b'K', 0x03, 0x04] => Ok(Format::Zip),
+        _ => anyhow::bail!("Unrecognized archive format: {}", archive_path.display()),
+    }
+}
+
+fn unpack_tar(reader: impl Read, dest_dir: &Path, keep: &dyn Fn(&Path) -> bool) -> Result<()> {
+    let mut archive = tar::Archive::new(reader);
+    archive.set_preserve_permissions(false);
+
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        let path = entry.path()?.into_owned();
+        check_member_path(&path)?;
+
+        if let Some(target) = entry.link_name()? {
+            // Symlink targets are relative to the link's directory, hard link
+            // targets to the archive root
+            let resolved = match entry.header().entry_type() {
+                tar::EntryType::Symlink => path.parent().unwrap_or(Path::new("")).join(&target),
+                _ => target.into_owned(),
+            };
+            if !is_enclosed(&resolved) {
+                anyhow::bail!(
+                    "Archive member {} links outside the extraction directory",
+                    path.display()
+                );
+            }
+        }
+
+        if keep(&path) {
+            entry.unpack_in(dest_dir)?;
+        }
+    }
+    Ok(())
+}
+
+fn unpack_zip(archive_path: &Path, dest_dir: &Path, keep: &dyn Fn(&Path) -> bool) -> Result<()> {
+    let file = File::open(archive_path)?;
+    let mut archive = zip::ZipArchive::new(BufReader::new(file))?;
+
+    for i in 0..archive.len() {
+        let mut member = archive.by_index(i)?;
+        check_member_path(Path::new(member.name()))?;
+        let Some(path) = member.enclosed_name() else {
+            anyhow::bail!("Invalid archive member name: {}", member.name());
+        };
+        if !keep(&path) {
+            continue;
+        }
+
+        let target = dest_dir.join(&path);
+        if member.is_dir() {
+            fs::create_dir_all(&target)?;
+        } else {
+            if let Some(parent) = target.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            io::copy(&mut member, &mut File::create(&target)?)?;
+        }
+    }
+    Ok(())
+}
+
+/// Reject member paths that are absolute or climb out of the extraction
+/// directory
+fn check_member_path(path: &Path) -> Result<()> {
+    let climbs = path.components().any(|c| c == Component::ParentDir);
+    if climbs || !is_enclosed(path) {
+        anyhow::bail!(
+            "Archive member {} escapes the extraction directory",
+            path.display()
+        );
+    }
+    Ok(())
+}
+
+/// Whether a relative path stays below its starting directory once `..`
+/// components are applied
+fn is_enclosed(path: &Path) -> bool {
+    let mut depth = 0usize;
+    for component in path.components() {
+        match component {
+            Component::Normal(_) => depth += 1,
+            Component::CurDir => {}
+            Component::ParentDir => match depth.checked_sub(1) {
+                Some(d) => depth = d,
+                None => return false,
+            },
+            Component::RootDir | Component::Prefix(_) => return false,
+        }
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    /// Build a tar archive with a single member, writing the name straight
+    /// into the header so that the tar crate's own path checks are bypassed
+    fn tar_with_member(name: &str, entry_type: tar::EntryType, link: Option<&str>) -> Vec<u8> {
+        let mut header = tar::Header::new_old();
+        header.as_old_mut().name[..name.len()].copy_from_slice(name.as_bytes());
+        if let Some(link) = link {
+            header.as_old_mut().linkname[..link.len()].copy_from_slice(link.as_bytes());
+        }
+        header.set_entry_type(entry_type);
+        header.set_mode(0o644);
+        header.set_size(if link.is_some() { 0 } else { 4 });
+        header.set_cksum();
+
+        let mut builder = tar::Builder::new(Vec::new());
+        let data: &[u8] = if link.is_some() { b"" } else { b"evil" };
+        builder.append(&header, data).unwrap();
+        builder.into_inner().unwrap()
+    }
+
+    fn extract_bytes(file_name: &str, bytes: &[u8]) -> (TempDir, Result<()>) {
+        let dir = TempDir::new().unwrap();
+        let archive = dir.path().join(file_name);
+        fs::write(&archive, bytes).unwrap();
+        let dest = dir.path().join("out");
+        fs::create_dir(&dest).unwrap();
+        let result = extract(&archive, &dest);
+        (dir, result)
+    }
+
+    #[test]
+    fn test_rejects_escaping_tar_members() {
+        let hostile = [
+            tar_with_member("../evil", tar::EntryType::Regular, None),
+            tar_with_member("pkg/../../evil", tar::EntryType::Regular, None),
+            tar_with_member("/tmp/evil", tar::EntryType::Regular, None),
+            tar_with_member("pkg/link", tar::EntryType::Symlink, Some("/etc/passwd")),
+            tar_with_member("pkg/link", tar::EntryType::Symlink, Some("../../etc")),
+            tar_with_member("pkg/link", tar::EntryType::Link, Some("../etc/passwd")),
+        ];
+        for bytes in hostile {
+            let (dir, result) = extract_bytes("pkg.tar", &bytes);
+            assert!(result.is_err());
+            assert!(!dir.path().join("evil").exists());
+        }
+
+        // Links that stay inside the archive are fine
+        let bytes = tar_with_member(
+            "pkg/R/link",
+            tar::EntryType::Symlink,
+            Some("../DESCRIPTION"),
+        );
+        assert!(extract_bytes("pkg.tar", &bytes).1.is_ok());
+    }
+
+    #[test]
+    fn test_rejects_escaping_zip_members() {
+        let mut writer = zip::ZipWriter::new(io::Cursor::new(Vec::new()));
+        writer
+            .start_file("../evil.py", zip::write::SimpleFileOptions::default())
+            .unwrap();
+        io::Write::write_all(&mut writer, b"import os").unwrap();
+        let bytes = writer.finish().unwrap().into_inner();
+
+        let (dir, result) = extract_bytes("pkg-1.0-py3-none-any.whl", &bytes);
+        assert!(result.is_err());
+        assert!(!dir.path().join("evil.py").exists());
+    }
+}
@@ -0,0 +1,89 @@
+//! Artifact checksums
+//!
+//! Every downloaded artifact is hashed with SHA-256 for the provenance
+//! record. Where the repository index publishes a digest (`MD5sum` in a
+//! `PACKAGES` file, `#sha256=` on a simple index link), the artifact is
+//! checked against it before extraction.
+
+use crate::error::FetchError;
+use anyhow::{Context, Result};
+use md5::Md5;
+use sha2::{Digest, Sha256};
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::Path;
+
+/// Digest published by a repository for one of its artifacts
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Checksum {
+    Md5(String),
+    Sha256(String),
+}
+
+/// Hash the file at `path`, check it against `expected` (if any) and return
+/// its SHA-256 as lowercase hex
+pub fn verify(path: &Path, expected: Option<&Checksum>) -> Result<String> {
+    let mut file =
+        File::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+    let mut sha256 = Sha256::new();
+    let mut md5 = Md5::new();
+    let mut buffer = [0u8; 64 * 1024];
+    loop {
+        let read = match file.read(&mut buffer) {
+            Ok(0) => break,
+            Ok(n) => n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e).with_context(|| format!("Failed to read {}", path.display())),
+        };
+        sha256.update(&buffer[..read]);
+        if matches!(expected, Some(Checksum::Md5(_))) {
+            md5.update(&buffer[..read]);
+        }
+    }
+
+    let sha256 = to_hex(&sha256.finalize());
+    let (algorithm, wanted, actual) = match expected {
+        None => return Ok(sha256),
+        Some(Checksum::Sha256(wanted)) => ("sha256", wanted, sha256.clone()),
+        Some(Checksum::Md5(wanted)) => ("md5", wanted, to_hex(&md5.finalize())),
+    };
+    if !wanted.eq_ignore_ascii_case(&actual) {
+        let file_name = path.file_name().unwrap_or(path.as_os_str());
+        return Err(FetchError::CorruptArchive {
+            file: file_name.to_string_lossy().into_owned(),
+            reason: format!("checksum mismatch, expected {algorithm} {wanted}, got {actual}"),
+        }
+        .into());
+    }
+    Ok(sha256)
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_verify_checksums() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("pkg.tar.gz");
+        std::fs::write(&path, "hello").unwrap();
+
+        let sha256 = "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824";
+        assert_eq!(verify(&path, None).unwrap(), sha256);
+        assert!(verify(&path, Some(&Checksum::Sha256(sha256.to_uppercase()))).is_ok());
+        assert!(verify(
+            &path,
+            Some(&Checksum::Md5("5d41402abc4b2a76b9719d911017c592".into()))
+        )
+        .is_ok());
+
+        let error = verify(&path, Some(&Checksum::Md5("0".repeat(32)))).unwrap_err();
+        assert!(error.to_string().contains("checksum mismatch"));
+        assert_eq!(crate::error::exit_code(&error), 6);
+    }
+}
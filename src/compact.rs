@@ -3,7 +3,7 @@
 //! When --compact is enabled, this module transforms records to minimize
 //! token count while preserving essential information for LLM usage.
 
-use crate::schema::{ClassRecord, FunctionRecord, PackageRecord, Record};
+use crate::schema::{ClassRecord, FunctionRecord, GenericRecord, PackageRecord, Record};
 
 /// Apply compact transformations to a list of records.
 pub fn compact_records(records: Vec<Record>) -> Vec<Record> {
@@ -15,6 +15,7 @@ fn compact_record(record: Record) -> Record {
         Record::ContextHeader(h) => Record::ContextHeader(h), // Keep headers as-is
         Record::Package(pkg) => Record::Package(compact_package(pkg)),
         Record::Function(func) => Record::Function(compact_function(func)),
+        Record::Generic(generic) => Record::Generic(compact_generic(generic)),
         Record::Class(cls) => Record::Class(compact_class(cls)),
         Record::Workflow(wf) => Record::Workflow(wf), // Keep workflows as-is
     }
@@ -52,6 +53,25 @@ fn compact_function(mut func: FunctionRecord) -> FunctionRecord {
     func
 }
 
+fn compact_generic(mut generic: GenericRecord) -> GenericRecord {
+    generic.purpose = generic.purpose.map(truncate_to_sentence);
+    generic.arguments = generic
+        .arguments
+        .into_iter()
+        .map(|(k, v)| (k, truncate_to_sentence(v)))
+        .collect();
+    generic.returns = generic.returns.map(truncate_to_sentence);
+    generic.examples.clear();
+
+    // Methods keep their class and signature
+    for method in &mut generic.methods {
+        method.purpose = None;
+        method.arguments.clear();
+    }
+
+    generic
+}
+
 fn compact_class(mut cls: ClassRecord) -> ClassRecord {
     // Truncate method descriptions
     cls.methods = cls
@@ -0,0 +1,146 @@
+//! Debian Control File (DCF) parsing
+//!
+//! R stores package metadata in DCF: DESCRIPTION files and the `PACKAGES`
+//! indexes of CRAN-like repositories. A file is a sequence of paragraphs
+//! separated by blank lines; each paragraph holds `Field: value` entries
+//! whose values may continue on indented lines.
+
+use anyhow::{Context, Result};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+/// One DCF paragraph (field name -> value)
+pub type Paragraph = BTreeMap<String, String>;
+
+/// Parse DCF content into paragraphs.
+///
+/// Continuation lines are joined with newlines after stripping their leading
+/// whitespace; a continuation line holding a single `.` stands for an empty
+/// line. Field names run up to the first colon and may contain any other
+/// non-whitespace character (`Authors@R`, `Config/testthat/edition`); lines
+/// that are neither fields nor continuations are ignored.
+pub fn parse(content: &str) -> Vec<Paragraph> {
+    let mut paragraphs = Vec::new();
+    let mut current = Paragraph::new();
+    let mut last_field: Option<String> = None;
+
+    for line in content.lines() {
+        let line = line.trim_end_matches('\r');
+
+        if line.trim().is_empty() {
+            finish_paragraph(&mut current, &mut paragraphs);
+            last_field = None;
+            continue;
+        }
+
+        if line.starts_with(' ') || line.starts_with('\t') {
+            if let Some(value) = last_field.as_ref().and_then(|f| current.get_mut(f)) {
+                let continuation = line.trim();
+                value.push('\n');
+                if continuation != "." {
+                    value.push_str(continuation);
+                }
+            }
+            continue;
+        }
+
+        last_field = None;
+        if let Some((field, value)) = line.split_once(':') {
+            if !field.is_empty() && !field.contains(char::is_whitespace) {
+                current.insert(field.to_string(), value.trim().to_string());
+                last_field = Some(field.to_string());
+            }
+        }
+    }
+    finish_paragraph(&mut current, &mut paragraphs);
+
+    paragraphs
+}
+
+/// Close the paragraph being read; values that start on the line after
+/// their field name (`Authors@R:` followed by indented code) lose the
+/// leading line break
+fn finish_paragraph(current: &mut Paragraph, paragraphs: &mut Vec<Paragraph>) {
+    if current.is_empty() {
+        return;
+    }
+    for value in current.values_mut() {
+        let trimmed = value.trim_matches('\n');
+        if trimmed.len() != value.len() {
+            *value = trimmed.to_string();
+        }
+    }
+    paragraphs.push(std::mem::take(current));
+}
+
+/// Read the first paragraph of a DCF file such as DESCRIPTION.
+///
+/// Files are decoded as UTF-8, or as Latin-1 when they are not valid UTF-8
+/// (older packages declare `Encoding: latin1`).
+pub fn read(path: &Path) -> Result<Paragraph> {
+    let bytes =
+        std::fs::read(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    let content = match String::from_utf8(bytes) {
+        Ok(content) => content,
+        Err(e) => e.into_bytes().iter().map(|&b| char::from(b)).collect(),
+    };
+    Ok(parse(&content).into_iter().next().unwrap_or_default())
+}
+
+/// Split a comma-separated field (`Imports`, `URL`) into its entries, with
+/// whitespace (including line breaks) collapsed
+pub fn split_list(value: &str) -> Vec<String> {
+    value
+        .split(',')
+        .map(|entry| entry.split_whitespace().collect::<Vec<_>>().join(" "))
+        .filter(|entry| !entry.is_empty())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_paragraphs_and_continuations() {
+        let content = "Package: foo\nVersion: 1.0.0\nImports: bar,\n    baz (>= 1.2)\n\nPackage: qux\nVersion: 0.1\n";
+        let paragraphs = parse(content);
+
+        assert_eq!(paragraphs.len(), 2);
+        assert_eq!(paragraphs[0]["Package"], "foo");
+        assert_eq!(paragraphs[0]["Imports"], "bar,\nbaz (>= 1.2)");
+        assert_eq!(paragraphs[1]["Version"], "0.1");
+    }
+
+    #[test]
+    fn test_parse_description_fields() {
+        let content = "Package: foo\nAuthors@R:\n    person(\"A\", \"B\",\n           role = c(\"aut\", \"cre\"))\nDescription: Does things: many\n    of them.\n    .\n    More.\nConfig/testthat/edition: 3\nnot a field\n  orphan continuation\nImports:\n    rlang (>=\n      1.0.0),\n    vctrs,\n";
+        let paragraph = &parse(content)[0];
+
+        assert_eq!(
+            paragraph["Authors@R"],
+            "person(\"A\", \"B\",\nrole = c(\"aut\", \"cre\"))"
+        );
+        assert_eq!(
+            paragraph["Description"],
+            "Does things: many\nof them.\n\nMore."
+        );
+        assert_eq!(paragraph["Config/testthat/edition"], "3");
+        assert_eq!(paragraph.len(), 5);
+        assert_eq!(
+            split_list(&paragraph["Imports"]),
+            ["rlang (>= 1.0.0)", "vctrs"]
+        );
+    }
+
+    #[test]
+    fn test_read_latin1_description() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("DESCRIPTION");
+        std::fs::write(&path, b"Package: caf\xe9\nEncoding: latin1\n").unwrap();
+
+        let paragraph = read(&path).unwrap();
+        assert_eq!(paragraph["Package"], "caf\u{e9}");
+        assert_eq!(paragraph["Encoding"], "latin1");
+    }
+}
@@ -0,0 +1,180 @@
+//! Dependency sections for `--with-deps`
+//!
+//! A package that re-exports or returns objects from its dependencies is only
+//! partly described by its own API. With `--with-deps`, the exported API of
+//! each dependency is appended to the record stream, reduced to the functions
+//! and classes the depending package's source actually refers to. Every
+//! function and class record then names its package.
+
+use crate::project::{self, Dependency};
+use crate::python_metadata;
+use crate::schema::Record;
+use std::collections::HashSet;
+use std::path::Path;
+
+/// Direct dependencies of a package: Imports and Depends for R, the
+/// requirements of its project metadata (without extras) for Python
+pub fn direct_dependencies(source_path: &Path, language: &str) -> Vec<Dependency> {
+    if language == "R" {
+        return std::fs::read_to_string(source_path.join("DESCRIPTION"))
+            .map(|content| project::description_dependencies(&content, &["Depends", "Imports"]))
+            .unwrap_or_default();
+    }
+
+    python_metadata::read(source_path)
+        .dependencies
+        .iter()
+        .filter_map(|requirement| project::parse_requirement(requirement))
+        .collect()
+}
+
+/// Every identifier in the source of a package (R code and NAMESPACE, or
+/// Python modules), which is what dependency APIs are reduced to
+pub fn referenced_names(source_path: &Path, language: &str) -> HashSet<String> {
+    let (pattern, roots) = if language == "R" {
+        // Syntactic names and %op% operators
+        (
+            r"[A-Za-z.][A-Za-z0-9._]*|%[^%\s]+%",
+            vec![source_path.join("R"), source_path.join("NAMESPACE")],
+        )
+    } else {
+        (r"[A-Za-z_][A-Za-z0-9_]*", vec![source_path.to_path_buf()])
+    };
+    let identifier = regex::Regex::new(pattern).expect("valid regex");
+    let extensions: &[&str] = if language == "R" {
+        &["R", "r"]
+    } else {
+        &["py"]
+    };
+
+    let mut names = HashSet::new();
+    let mut pending = roots;
+    while let Some(path) = pending.pop() {
+        if path.is_dir() {
+            if let Ok(entries) = std::fs::read_dir(&path) {
+                pending.extend(entries.filter_map(Result::ok).map(|e| e.path()));
+            }
+            continue;
+        }
+        let is_source = path.file_name().is_some_and(|n| n == "NAMESPACE")
+            || path
+                .extension()
+                .and_then(|e| e.to_str())
+                .is_some_and(|e| extensions.contains(&e));
+        if !is_source {
+            continue;
+        }
+        if let Ok(content) = std::fs::read_to_string(&path) {
+            names.extend(
+                identifier
+                    .find_iter(&content)
+                    .map(|m| m.as_str().to_string()),
+            );
+        }
+    }
+    names
+}
+
+/// Reduce the records of dependency `package` to its package record and the
+/// exported functions, generics and classes named in `referenced`
+pub fn reduce_to_referenced(
+    records: Vec<Record>,
+    referenced: &HashSet<String>,
+    package: &str,
+) -> Vec<Record> {
+    let records = records
+        .into_iter()
+        .filter(|record| match record {
+            Record::Package(_) => true,
+            Record::Function(func) => func.exported && referenced.contains(&func.name),
+            Record::Generic(generic) => generic.exported && referenced.contains(&generic.name),
+            Record::Class(cls) => referenced.contains(&cls.name),
+            Record::ContextHeader(_) | Record::Workflow(_) => false,
+        })
+        .collect();
+    tag_package(records, package)
+}
+
+/// Set the package of every function, generic and class record
+pub fn tag_package(mut records: Vec<Record>, package: &str) -> Vec<Record> {
+    for record in &mut records {
+        match record {
+            Record::Function(func) => func.package = Some(package.to_string()),
+            Record::Generic(generic) => generic.package = Some(package.to_string()),
+            Record::Class(cls) => cls.package = Some(package.to_string()),
+            _ => {}
+        }
+    }
+    records
+}
+
+/// Key identifying a package across spellings (`PyYAML` and `pyyaml`,
+/// `typing_extensions` and `typing-extensions`)
+pub fn package_key(name: &str) -> String {
+    name.to_ascii_lowercase().replace(['_', '.'], "-")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schema::FunctionRecord;
+    use std::collections::BTreeMap;
+
+    fn function(name: &str, exported: bool) -> Record {
+        Record::Function(FunctionRecord {
+            name: name.to_string(),
+            package: None,
+            exported,
+            signature: format!("{name}()"),
+            purpose: None,
+            role: None,
+            arguments: BTreeMap::new(),
+            arg_types: BTreeMap::new(),
+            returns: None,
+            return_type: None,
+            constraints: Vec::new(),
+            examples: Vec::new(),
+            related: Vec::new(),
+        })
+    }
+
+    #[test]
+    fn test_reduce_to_referenced_functions() {
+        let dir = tempfile::TempDir::new().unwrap();
+        std::fs::create_dir(dir.path().join("R")).unwrap();
+        std::fs::write(
+            dir.path().join("R/verbs.R"),
+            "summarise_all <- function(x) rlang::abort('no')\nx %||% y\n",
+        )
+        .unwrap();
+        std::fs::write(dir.path().join("NAMESPACE"), "importFrom(rlang, .data)\n").unwrap();
+        std::fs::write(
+            dir.path().join("DESCRIPTION"),
+            "Package: verbs\nDepends: R (>= 4.0), utils\nImports: rlang (>= 1.0), vctrs\nSuggests: testthat\n",
+        )
+        .unwrap();
+
+        let names: Vec<String> = direct_dependencies(dir.path(), "R")
+            .into_iter()
+            .map(|d| d.name)
+            .collect();
+        assert_eq!(names, ["rlang", "vctrs"]);
+
+        let referenced = referenced_names(dir.path(), "R");
+        let records = vec![
+            function("abort", true),
+            function("%||%", true),
+            function(".data", true),
+            function("warn", true),
+            function("summarise_all", false),
+        ];
+        let kept: Vec<String> = reduce_to_referenced(records, &referenced, "rlang")
+            .into_iter()
+            .map(|r| match r {
+                Record::Function(f) => format!("{}::{}", f.package.unwrap(), f.name),
+                _ => unreachable!(),
+            })
+            .collect();
+        assert_eq!(kept, ["rlang::abort", "rlang::%||%", "rlang::.data"]);
+    }
+}
@@ -0,0 +1,81 @@
+//! Typed fetch failures
+//!
+//! Fetch errors travel inside `anyhow` chains like every other error; the
+//! first [`FetchError`] in a chain decides the process exit code, so that
+//! scripts can tell a missing package from a network outage.
+
+/// Why a package source could not be obtained
+#[derive(Debug, thiserror::Error)]
+pub enum FetchError {
+    /// The package (or a file it needs) does not exist in the repository
+    #[error("{0}")]
+    NotFound(String),
+
+    /// The package exists, but not in the requested version
+    #[error("Version {version} of '{name}' not found in {repository}")]
+    VersionNotFound {
+        name: String,
+        version: String,
+        repository: String,
+    },
+
+    /// The repository could not be reached, or kept failing after retries
+    #[error("{0}")]
+    Network(String),
+
+    /// A downloaded or local archive failed verification or extraction
+    #[error("Corrupt archive {file}: {reason}")]
+    CorruptArchive { file: String, reason: String },
+}
+
+impl FetchError {
+    /// Process exit code for this failure (1 is left for all other errors,
+    /// 2 for usage errors)
+    pub fn exit_code(&self) -> u8 {
+        match self {
+            Self::NotFound(_) => 3,
+            Self::VersionNotFound { .. } => 4,
+            Self::Network(_) => 5,
+            Self::CorruptArchive { .. } => 6,
+        }
+    }
+}
+
+/// The first [`FetchError`] in an error chain
+fn find(error: &anyhow::Error) -> Option<&FetchError> {
+    error.chain().find_map(|e| e.downcast_ref::<FetchError>())
+}
+
+/// Exit code for an error that ended the run
+pub fn exit_code(error: &anyhow::Error) -> u8 {
+    find(error).map_or(1, FetchError::exit_code)
+}
+
+/// Whether an error means that the requested resource does not exist
+pub fn is_not_found(error: &anyhow::Error) -> bool {
+    matches!(find(error), Some(FetchError::NotFound(_)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Context;
+
+    #[test]
+    fn test_exit_code_from_wrapped_errors() {
+        let not_found: anyhow::Result<()> =
+            Err(FetchError::NotFound("HTTP 404 for https://example.org/x".to_string()).into());
+        let error = not_found.context("Failed to download x").unwrap_err();
+        assert!(is_not_found(&error));
+        assert_eq!(exit_code(&error), 3);
+
+        let corrupt = anyhow::Error::new(FetchError::CorruptArchive {
+            file: "x.tar.gz".to_string(),
+            reason: "unexpected end of file".to_string(),
+        });
+        assert!(!is_not_found(&corrupt));
+        assert_eq!(exit_code(&corrupt), 6);
+
+        assert_eq!(exit_code(&anyhow::anyhow!("something else")), 1);
+    }
+}
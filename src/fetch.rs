@@ -1,29 +1,200 @@
 //! Package source fetching
 //!
-//! Downloads R packages from CRAN/GitHub and Python packages from `PyPI`/GitHub.
-
+//! Downloads R packages from CRAN/Bioconductor, Python packages from `PyPI`,
+//! and packages of either language from git repositories (GitHub, GitLab,
+//! Codeberg or any git remote).
+//! Downloads, index lookups and archive extraction all happen in-process, so
+//! fetching does not need an R or Python toolchain.
+
+use crate::cache::Cache;
+use crate::checksum::{self, Checksum};
+use crate::error::{self, FetchError};
+use crate::schema::Provenance;
+use crate::{archive, auth, dcf, git, http, python_metadata, FetchOptions};
 use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::io::Read;
 use std::path::{Path, PathBuf};
-use std::process::Command;
 use tempfile::TempDir;
 
 /// Represents a package source location
 #[derive(Debug, Clone)]
 pub enum PackageSource {
-    /// CRAN package (e.g., "dplyr")
-    Cran(String),
-    /// Bioconductor package (e.g., "GenomicRanges")
-    Bioconductor(String),
-    /// `PyPI` package (e.g., "numpy")
-    PyPI(String),
-    /// GitHub repository (e.g., "tidyverse/dplyr" or "ropensci/rix")
-    GitHub {
-        owner: String,
-        repo: String,
+    /// CRAN package, optionally pinned (e.g., "dplyr" or "dplyr@1.0.10")
+    Cran {
+        name: String,
+        version: Option<String>,
+    },
+    /// Bioconductor package, optionally from a given release (e.g.,
+    /// "GenomicRanges" or "GenomicRanges@3.18")
+    Bioconductor {
+        name: String,
+        release: Option<String>,
+    },
+    /// r-universe package (e.g., "ropensci/rix")
+    RUniverse { owner: String, name: String },
+    /// `PyPI` package, optionally pinned (e.g., "numpy" or "requests==2.31.0")
+    PyPI {
+        name: String,
+        version: Option<String>,
+    },
+    /// Git repository (e.g., "github:tidyverse/dplyr"), optionally with the
+    /// package in a subdirectory (e.g., "github:apache/arrow/r")
+    Repository {
+        repository: Repository,
+        subdir: Option<String>,
         ref_: Option<String>,
     },
     /// Local path (e.g., "." or "./mypackage" or "/path/to/package")
     Local(PathBuf),
+    /// Local source archive or wheel (e.g., "`mypkg_1.2.0.tar.gz`" or "mypkg-1.2.0-py3-none-any.whl")
+    Archive(PathBuf),
+}
+
+/// Hosted git forge whose repositories can be downloaded as archives
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Forge {
+    GitHub,
+    GitLab,
+    Codeberg,
+}
+
+impl Forge {
+    /// Spec prefix of the forge (e.g. "github")
+    fn prefix(self) -> &'static str {
+        match self {
+            Self::GitHub => "github",
+            Self::GitLab => "gitlab",
+            Self::Codeberg => "codeberg",
+        }
+    }
+
+    /// Resolve `ref_` (the default branch if `None`) of `owner/repo` to a
+    /// commit SHA through the forge's API
+    fn resolve_commit(
+        self,
+        owner: &str,
+        repo: &str,
+        ref_: Option<&str>,
+        options: &FetchOptions,
+    ) -> Result<String> {
+        if let Some(sha) = ref_.filter(|r| is_commit_sha(r)) {
+            return Ok(sha.to_ascii_lowercase());
+        }
+
+        let commit = match self {
+            Self::GitHub => {
+                let ref_name = ref_.unwrap_or("HEAD");
+                let url = format!("https://api.github.com/repos/{owner}/{repo}/commits/{ref_name}");
+                http::get_json(&url)?["sha"].as_str().map(str::to_string)
+            }
+            Self::GitLab => {
+                let host = &options.gitlab_host;
+                let project = encode_component(&format!("{owner}/{repo}"));
+                let mut url = format!(
+                    "https://{host}/api/v4/projects/{project}/repository/commits?per_page=1"
+                );
+                if let Some(ref_name) = ref_ {
+                    url.push_str(&format!("&ref_name={}", encode_component(ref_name)));
+                }
+                http::get_json(&url)?[0]["id"].as_str().map(str::to_string)
+            }
+            Self::Codeberg => {
+                let mut url = format!(
+                    "https://codeberg.org/api/v1/repos/{owner}/{repo}/commits?limit=1&stat=false"
+                );
+                if let Some(ref_name) = ref_ {
+                    url.push_str(&format!("&sha={}", encode_component(ref_name)));
+                }
+                http::get_json(&url)?[0]["sha"].as_str().map(str::to_string)
+            }
+        };
+
+        commit.with_context(|| {
+            let ref_name = ref_.unwrap_or("the default branch");
+            format!("Could not resolve {ref_name} of {owner}/{repo} to a commit")
+        })
+    }
+
+    /// Web URL of `owner/repo`, which identifies it in the cache
+    fn web_url(self, owner: &str, repo: &str, options: &FetchOptions) -> String {
+        let host = match self {
+            Self::GitHub => "github.com",
+            Self::GitLab => &options.gitlab_host,
+            Self::Codeberg => "codeberg.org",
+        };
+        format!("https://{host}/{owner}/{repo}")
+    }
+
+    /// URL of a tarball of `owner/repo` at `commit`
+    fn archive_url(self, owner: &str, repo: &str, commit: &str, options: &FetchOptions) -> String {
+        match self {
+            // The API endpoint also serves private repositories to token holders
+            Self::GitHub => {
+                format!("https://api.github.com/repos/{owner}/{repo}/tarball/{commit}")
+            }
+            Self::GitLab => {
+                let host = &options.gitlab_host;
+                let project = encode_component(&format!("{owner}/{repo}"));
+                format!(
+                    "https://{host}/api/v4/projects/{project}/repository/archive.tar.gz?sha={commit}"
+                )
+            }
+            Self::Codeberg => {
+                format!("https://codeberg.org/api/v1/repos/{owner}/{repo}/archive/{commit}.tar.gz")
+            }
+        }
+    }
+}
+
+/// Percent-encode a string for use as a single URL path segment or query value
+fn encode_component(s: &str) -> String {
+    url::form_urlencoded::byte_serialize(s.as_bytes()).collect()
+}
+
+/// Whether `s` is a full (40 hex digit) git commit SHA
+fn is_commit_sha(s: &str) -> bool {
+    s.len() == 40 && s.bytes().all(|b| b.is_ascii_hexdigit())
+}
+
+/// Repository holding a package
+#[derive(Debug, Clone)]
+pub enum Repository {
+    /// Repository on a hosted forge, downloaded as an archive
+    Forge {
+        forge: Forge,
+        owner: String,
+        repo: String,
+    },
+    /// Any git remote, fetched with git (e.g., "<https://git.example.com/pkg.git>")
+    Remote(String),
+    /// Local git repository, read from its object database
+    Local(PathBuf),
+}
+
+impl Repository {
+    /// Name to fall back on when the package metadata has none
+    fn default_name(&self) -> String {
+        let name = match self {
+            Self::Forge { repo, .. } => repo.as_str(),
+            Self::Remote(url) => url.trim_end_matches('/').rsplit('/').next().unwrap_or(url),
+            Self::Local(path) => path
+                .file_name()
+                .and_then(|n| n.to_str())
+                .unwrap_or("unknown"),
+        };
+        name.trim_end_matches(".git").to_string()
+    }
+}
+
+impl std::fmt::Display for Repository {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Forge { forge, owner, repo } => write!(f, "{}:{owner}/{repo}", forge.prefix()),
+            Self::Remote(url) => write!(f, "{}", auth::redact(url)),
+            Self::Local(path) => write!(f, "{}", path.display()),
+        }
+    }
 }
 
 impl PackageSource {
@@ -31,14 +202,22 @@ impl PackageSource {
     ///
     /// Formats:
     /// - `dplyr` (CRAN for R, `PyPI` for Python)
-    /// - `github:owner/repo` or `github:owner/repo@ref`
+    /// - `dplyr@1.0.10` (pinned CRAN version) or `requests==2.31.0` (pinned `PyPI` version)
+    /// - `runiverse:owner/pkg` (r-universe)
+    /// - `github:owner/repo` or `github:owner/repo@ref` (likewise `gitlab:` and `codeberg:`)
+    /// - `github:owner/repo/path/to/pkg[@ref]` (package in a subdirectory)
+    /// - `git+https://host/path/repo.git[@rev][#subdirectory=path]` (any git remote)
+    /// - `git:/path/to/repo[@rev][#subdirectory=path]` (local git repository)
     /// - `.` or `./path` or `/path` or `~/path` (local path)
+    /// - `mypkg_1.2.0.tar.gz`, `mypkg-1.2.0.zip` or `mypkg-1.2.0-py3-none-any.whl` (local archive)
     pub fn parse(spec: &str, language: &str) -> Result<Self> {
-        // Check for local path indicators
+        // Check for local path indicators; archive file names count as paths
+        // even without a leading ./
         if spec.starts_with('.')
             || spec.starts_with('/')
             || spec.starts_with('~')
             || spec.starts_with("local:")
+            || (archive::is_archive_name(spec) && Path::new(spec).is_file())
         {
             let path_str = spec.strip_prefix("local:").unwrap_or(spec);
             let path = if path_str.starts_with('~') {
@@ -53,38 +232,356 @@ impl PackageSource {
                 .canonicalize()
                 .with_context(|| format!("Local path does not exist: {}", path.display()))?;
 
+            if canonical.is_file() {
+                return Ok(Self::Archive(canonical));
+            }
             return Ok(Self::Local(canonical));
         }
 
-        if let Some(rest) = spec.strip_prefix("github:") {
+        let forges = [Forge::GitHub, Forge::GitLab, Forge::Codeberg];
+        if let Some((forge, rest)) = forges.iter().find_map(|&forge| {
+            spec.strip_prefix(forge.prefix())
+                .and_then(|r| r.strip_prefix(':'))
+                .map(|rest| (forge, rest))
+        }) {
             let (repo_part, ref_) = rest.find('@').map_or((rest, None), |at_pos| {
                 (&rest[..at_pos], Some(rest[at_pos + 1..].to_string()))
             });
 
-            let parts: Vec<&str> = repo_part.split('/').collect();
-            if parts.len() != 2 {
-                anyhow::bail!("Invalid GitHub spec: expected 'github:owner/repo', got '{spec}'");
+            let parts: Vec<&str> = repo_part.trim_end_matches('/').split('/').collect();
+            if parts.len() < 2 || !parts.iter().all(|p| is_path_component(p)) {
+                anyhow::bail!(
+                    "Invalid {0} spec: expected '{0}:owner/repo[/subdir]', got '{spec}'",
+                    forge.prefix()
+                );
+            }
+            if let Some(ref_) = &ref_ {
+                validate_git_ref(ref_)?;
+            }
+
+            Ok(Self::Repository {
+                repository: Repository::Forge {
+                    forge,
+                    owner: parts[0].to_string(),
+                    repo: parts[1].to_string(),
+                },
+                subdir: (parts.len() > 2).then(|| parts[2..].join("/")),
+                ref_,
+            })
+        } else if let Some(url) = spec.strip_prefix("git+") {
+            let (url, subdir) = split_subdirectory(url)?;
+            // The revision follows the last '@' of the path, so that
+            // credentials in the authority (user@host) are left alone
+            let path_start = url
+                .find("://")
+                .and_then(|i| url[i + 3..].find('/').map(|j| i + 3 + j))
+                .with_context(|| format!("Invalid git URL: '{}'", auth::redact(url)))?;
+            let (url, ref_) = match url[path_start..].rfind('@') {
+                Some(at) => (
+                    &url[..path_start + at],
+                    Some(url[path_start + at + 1..].to_string()),
+                ),
+                None => (url, None),
+            };
+            if let Some(ref_) = &ref_ {
+                validate_git_ref(ref_)?;
             }
 
-            Ok(Self::GitHub {
-                owner: parts[0].to_string(),
-                repo: parts[1].to_string(),
+            Ok(Self::Repository {
+                repository: Repository::Remote(url.to_string()),
+                subdir,
+                ref_,
+            })
+        } else if let Some(path) = spec.strip_prefix("git:") {
+            let (path, subdir) = split_subdirectory(path)?;
+            let expand_home = |path: &str| -> Result<PathBuf> {
+                Ok(match path.strip_prefix('~') {
+                    Some(rest) => {
+                        let home =
+                            std::env::var("HOME").context("HOME environment variable not set")?;
+                        PathBuf::from(format!("{home}{rest}"))
+                    }
+                    None => PathBuf::from(path),
+                })
+            };
+            // Paths may contain '@' themselves: the revision follows the
+            // last '@' only when the whole spec is not an existing path
+            let (path, ref_) = match path.rsplit_once('@') {
+                Some((repo_path, rev)) if !expand_home(path)?.exists() => {
+                    validate_git_ref(rev)?;
+                    (expand_home(repo_path)?, Some(rev.to_string()))
+                }
+                _ => (expand_home(path)?, None),
+            };
+            let canonical = path
+                .canonicalize()
+                .with_context(|| format!("Local path does not exist: {}", path.display()))?;
+
+            Ok(Self::Repository {
+                repository: Repository::Local(canonical),
+                subdir,
                 ref_,
             })
         } else {
-            if let Some(name) = spec.strip_prefix("bioc:") {
-                return Ok(Self::Bioconductor(name.to_string()));
+            if let Some(rest) = spec.strip_prefix("bioc:") {
+                let (name, release) = split_pinned_version(rest, "@")?;
+                validate_r_package_name(&name)?;
+                if let Some(release) = &release {
+                    validate_bioconductor_release(release)?;
+                }
+                return Ok(Self::Bioconductor { name, release });
+            }
+
+            if let Some(rest) = spec.strip_prefix("runiverse:") {
+                return match rest.split_once('/') {
+                    Some((owner, name)) if is_path_component(owner) => {
+                        validate_r_package_name(name)?;
+                        Ok(Self::RUniverse {
+                            owner: owner.to_string(),
+                            name: name.to_string(),
+                        })
+                    }
+                    _ => anyhow::bail!(
+                        "Invalid r-universe spec: expected 'runiverse:owner/pkg', got '{spec}'"
+                    ),
+                };
             }
 
             match language {
-                "r" | "R" => Ok(Self::Cran(spec.to_string())),
-                "python" | "Python" => Ok(Self::PyPI(spec.to_string())),
+                "r" | "R" => {
+                    let (name, version) = split_pinned_version(spec, "@")?;
+                    validate_r_package_name(&name)?;
+                    Ok(Self::Cran { name, version })
+                }
+                "python" | "Python" => {
+                    let (name, version) = split_pinned_version(spec, "==")?;
+                    validate_python_package_name(&name)?;
+                    Ok(Self::PyPI { name, version })
+                }
                 _ => anyhow::bail!("Unknown language: {language}"),
             }
         }
     }
 }
 
+/// Split a `#subdirectory=path` fragment (pip's syntax) off a git spec
+fn split_subdirectory(spec: &str) -> Result<(&str, Option<String>)> {
+    let Some((rest, fragment)) = spec.split_once('#') else {
+        return Ok((spec, None));
+    };
+    let subdir = fragment
+        .strip_prefix("subdirectory=")
+        .with_context(|| {
+            format!("Unsupported fragment '#{fragment}': expected '#subdirectory=path'")
+        })?
+        .trim_matches('/');
+    if subdir.is_empty()
+        || subdir
+            .split('/')
+            .any(|p| p.is_empty() || p == "." || p == "..")
+    {
+        anyhow::bail!("Invalid subdirectory: '{subdir}'");
+    }
+    Ok((rest, Some(subdir.to_string())))
+}
+
+/// Split a `name<sep>version` specifier into its name and optional pinned version
+fn split_pinned_version(spec: &str, separator: &str) -> Result<(String, Option<String>)> {
+    match spec.split_once(separator) {
+        Some((name, version)) => {
+            let (name, version) = (name.trim(), version.trim());
+            if name.is_empty() || version.is_empty() {
+                anyhow::bail!(
+                    "Invalid package spec: expected 'name{separator}version', got '{spec}'"
+                );
+            }
+            validate_version(version)?;
+            Ok((name.to_string(), Some(version.to_string())))
+        }
+        None => Ok((spec.trim().to_string(), None)),
+    }
+}
+
+/// Check a Bioconductor release: major and minor version (e.g. "3.18")
+fn validate_bioconductor_release(release: &str) -> Result<()> {
+    let valid = release.split_once('.').is_some_and(|(major, minor)| {
+        [major, minor]
+            .iter()
+            .all(|p| !p.is_empty() && p.chars().all(|c| c.is_ascii_digit()))
+    });
+    if !valid {
+        anyhow::bail!("Invalid Bioconductor release: '{release}' (expected e.g. '3.18')");
+    }
+    Ok(())
+}
+
+/// Check an R package name against CRAN's grammar: ASCII letters, digits and
+/// dots, starting with a letter and not ending with a dot
+fn validate_r_package_name(name: &str) -> Result<()> {
+    let valid = name.starts_with(|c: char| c.is_ascii_alphabetic())
+        && !name.ends_with('.')
+        && name.len() >= 2
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '.');
+    if !valid {
+        anyhow::bail!("Invalid R package name: '{name}'");
+    }
+    Ok(())
+}
+
+/// Check a Python distribution name against PEP 508: ASCII letters, digits,
+/// `.`, `_` and `-`, starting and ending with a letter or digit
+fn validate_python_package_name(name: &str) -> Result<()> {
+    let valid = name.starts_with(|c: char| c.is_ascii_alphanumeric())
+        && name.ends_with(|c: char| c.is_ascii_alphanumeric())
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '_' | '-'));
+    if !valid {
+        anyhow::bail!("Invalid Python package name: '{name}'");
+    }
+    Ok(())
+}
+
+/// Check a pinned version: ASCII letters, digits and `.`, `-`, `_`, `+`, `!`
+/// (enough for R versions and PEP 440)
+fn validate_version(version: &str) -> Result<()> {
+    let valid = version.starts_with(|c: char| c.is_ascii_alphanumeric())
+        && version
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '_' | '+' | '!'));
+    if !valid {
+        anyhow::bail!("Invalid version: '{version}'");
+    }
+    Ok(())
+}
+
+/// Check a git ref or revision, following the rules of `git check-ref-format`
+/// that matter here: no option-like, whitespace or control characters, and no
+/// `..` that could walk a URL path
+fn validate_git_ref(ref_: &str) -> Result<()> {
+    let valid = !ref_.is_empty()
+        && !ref_.starts_with(['-', '/'])
+        && !ref_.contains("..")
+        && !ref_
+            .chars()
+            .any(|c| c.is_control() || c.is_whitespace() || "~^:?*[\\".contains(c));
+    if !valid {
+        anyhow::bail!("Invalid git ref: '{ref_}'");
+    }
+    Ok(())
+}
+
+/// Parse a `--gitlab-host` value: a host name with an optional port
+pub(crate) fn parse_host(host: &str) -> Result<String> {
+    let parsed = url::Url::parse(&format!("https://{host}/")).ok();
+    let valid = parsed.is_some_and(|url| {
+        url.host_str().is_some()
+            && url.username().is_empty()
+            && url.password().is_none()
+            && url.path() == "/"
+            && url.query().is_none()
+            && url.fragment().is_none()
+    });
+    if !valid {
+        anyhow::bail!("Invalid host: '{host}' (expected e.g. 'gitlab.example.com')");
+    }
+    Ok(host.to_ascii_lowercase())
+}
+
+/// Whether `s` is a plain path component (owner, repository or directory
+/// name) that cannot change the meaning of the URL or path it is joined into
+fn is_path_component(s: &str) -> bool {
+    !s.is_empty()
+        && s != "."
+        && s != ".."
+        && s.chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '_' | '-'))
+}
+
+/// Compare two version strings component by component.
+///
+/// Runs of digits compare numerically and runs of letters lexically. Letter
+/// runs mark pre-releases (`dev` before `a`/`b`/`rc`), which sort before the
+/// end of a version, or post-releases (`post`), which sort after it; numeric
+/// components sort after all of them.
+pub(crate) fn compare_versions(a: &str, b: &str) -> std::cmp::Ordering {
+    use std::cmp::Ordering;
+
+    /// Rank of a letter run relative to the end of a version (2)
+    fn rank(run: &str) -> u8 {
+        if run.eq_ignore_ascii_case("dev") {
+            0
+        } else if run.eq_ignore_ascii_case("post") {
+            3
+        } else {
+            1
+        }
+    }
+    const END: u8 = 2;
+
+    let (a, b) = (version_components(a), version_components(b));
+    for i in 0..a.len().max(b.len()) {
+        let ordering = match (a.get(i), b.get(i)) {
+            (Some(x), Some(y)) => match (is_numeric(x), is_numeric(y)) {
+                (true, true) => {
+                    let (x, y) = (x.trim_start_matches('0'), y.trim_start_matches('0'));
+                    x.len().cmp(&y.len()).then_with(|| x.cmp(y))
+                }
+                (true, false) => Ordering::Greater,
+                (false, true) => Ordering::Less,
+                (false, false) => rank(x).cmp(&rank(y)).then_with(|| x.cmp(y)),
+            },
+            (Some(x), None) if is_numeric(x) => Ordering::Greater,
+            (Some(x), None) => rank(x).cmp(&END),
+            (None, Some(y)) if is_numeric(y) => Ordering::Less,
+            (None, Some(y)) => END.cmp(&rank(y)),
+            (None, None) => Ordering::Equal,
+        };
+        if ordering != Ordering::Equal {
+            return ordering;
+        }
+    }
+    Ordering::Equal
+}
+
+/// Whether a version is a final release: only post-release letter runs are
+/// allowed, as in `2.0.post1`
+fn is_final_version(version: &str) -> bool {
+    version_components(version)
+        .iter()
+        .all(|c| is_numeric(c) || c.eq_ignore_ascii_case("post"))
+}
+
+fn is_numeric(component: &str) -> bool {
+    component.bytes().all(|c| c.is_ascii_digit())
+}
+
+/// Split a version into runs of digits and runs of letters
+fn version_components(v: &str) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut start = None;
+    let mut digit_run = false;
+    for (i, c) in v.char_indices() {
+        if c.is_ascii_alphanumeric() {
+            match start {
+                Some(s) if digit_run != c.is_ascii_digit() => {
+                    parts.push(&v[s..i]);
+                    start = Some(i);
+                }
+                None => start = Some(i),
+                _ => {}
+            }
+            digit_run = c.is_ascii_digit();
+        } else if let Some(s) = start.take() {
+            parts.push(&v[s..i]);
+        }
+    }
+    if let Some(s) = start {
+        parts.push(&v[s..]);
+    }
+    parts
+}
+
 /// Common trait for package information (local or fetched)
 pub trait PackageInfo {
     /// Get the path to the package source directory
@@ -93,19 +590,71 @@ pub trait PackageInfo {
     fn name(&self) -> &str;
     /// Get the package version, if available
     fn version(&self) -> Option<&str>;
+    /// Get details about where the package was fetched from, if known
+    fn origin(&self) -> Option<&Origin> {
+        None
+    }
+}
+
+/// Details about where a fetched package came from, kept in the cache
+/// alongside the source
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Origin {
+    /// Kind of distribution the source was taken from (e.g. "sdist", "wheel")
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub artifact: Option<String>,
+    /// Upstream commit SHA the source was built from
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub commit: Option<String>,
+    /// URL the source was downloaded from
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub source_url: Option<String>,
+    /// SHA-256 of the downloaded artifact
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub sha256: Option<String>,
+    /// How the source was obtained (e.g. "cran", "cran-archive", "pypi", "git")
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub fetch_method: Option<String>,
+    /// Bioconductor release whose repositories provided the package
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub bioconductor_release: Option<String>,
+}
+
+impl Origin {
+    /// Origin of a source obtained by `fetch_method`
+    fn fetched(fetch_method: &str) -> Self {
+        Self {
+            fetch_method: Some(fetch_method.to_string()),
+            ..Self::default()
+        }
+    }
+
+    /// Provenance fields for the package record, without any credentials
+    /// embedded in the source URL
+    pub fn provenance(&self) -> Provenance {
+        Provenance {
+            source_url: self.source_url.as_deref().map(auth::redact),
+            sha256: self.sha256.clone(),
+            fetch_method: self.fetch_method.clone(),
+            pkgctx_version: env!("CARGO_PKG_VERSION").to_string(),
+        }
+    }
 }
 
 /// Downloaded package with extracted source
 pub struct FetchedPackage {
-    /// Temporary directory containing extracted source (kept alive by RAII)
+    /// Temporary directory containing extracted source (kept alive by RAII);
+    /// `None` when the source lives in the on-disk cache
     #[allow(dead_code)]
-    temp_dir: TempDir,
+    temp_dir: Option<TempDir>,
     /// Path to the package source root
     pub source_path: PathBuf,
     /// Package name
     pub name: String,
     /// Package version (if available)
     pub version: Option<String>,
+    /// Where the package was fetched from
+    pub origin: Origin,
 }
 
 impl PackageInfo for FetchedPackage {
@@ -120,6 +669,10 @@ impl PackageInfo for FetchedPackage {
     fn version(&self) -> Option<&str> {
         self.version.as_deref()
     }
+
+    fn origin(&self) -> Option<&Origin> {
+        Some(&self.origin)
+    }
 }
 
 /// A local package reference (no temp directory needed)
@@ -130,6 +683,8 @@ pub struct LocalPackage {
     pub name: String,
     /// Package version (if available)
     pub version: Option<String>,
+    /// Where the package was read from
+    pub origin: Origin,
 }
 
 impl PackageInfo for LocalPackage {
@@ -144,274 +699,894 @@ impl PackageInfo for LocalPackage {
     fn version(&self) -> Option<&str> {
         self.version.as_deref()
     }
+
+    fn origin(&self) -> Option<&Origin> {
+        Some(&self.origin)
+    }
 }
 
-/// Convert a path to a string, returning an error if it contains non-UTF8 characters
-fn path_to_str(path: &Path) -> Result<&str> {
-    path.to_str()
-        .with_context(|| format!("Path contains non-UTF8 characters: {}", path.display()))
+/// Whether a repository URL points at a local mirror
+fn is_local_url(url: &str) -> bool {
+    url.starts_with("file://")
 }
 
-/// Fetch an R package from CRAN
-pub fn fetch_cran_package(name: &str) -> Result<FetchedPackage> {
-    let temp_dir = TempDir::new().context("Failed to create temp directory")?;
-    let destdir = path_to_str(temp_dir.path())?;
-
-    // Use R to download the package
-    let r_script = format!(
-        r#"
-        pkg <- "{name}"
-        destdir <- "{destdir}"
-        
-        # Get package info from CRAN
-        available <- available.packages(repos = "https://cloud.r-project.org")
-        if (!(pkg %in% rownames(available))) {{
-            stop(paste("Package", pkg, "not found on CRAN"))
-        }}
-        
-        version <- available[pkg, "Version"]
-        
-        # Download source tarball
-        url <- paste0("https://cloud.r-project.org/src/contrib/", pkg, "_", version, ".tar.gz")
-        destfile <- file.path(destdir, paste0(pkg, "_", version, ".tar.gz"))
-        download.file(url, destfile, quiet = TRUE)
-        
-        # Extract
-        untar(destfile, exdir = destdir)
-        
-        # Output info
-        cat("VERSION:", version, "\n")
-        cat("PATH:", file.path(destdir, pkg), "\n")
-    "#
-    );
-
-    let output = Command::new("Rscript")
-        .args(["--vanilla", "-e", &r_script])
-        .output()
-        .context("Failed to run Rscript for CRAN download")?;
-
-    if !output.status.success() {
-        let stderr = String::from_utf8_lossy(&output.stderr);
-        anyhow::bail!("Failed to download CRAN package '{name}': {stderr}");
-    }
-
-    let stdout = String::from_utf8_lossy(&output.stdout);
-    let mut version = None;
-    let mut source_path = temp_dir.path().join(name);
-
-    for line in stdout.lines() {
-        if let Some(v) = line.strip_prefix("VERSION: ") {
-            version = Some(v.trim().to_string());
-        }
-        if let Some(p) = line.strip_prefix("PATH: ") {
-            source_path = PathBuf::from(p.trim());
-        }
+/// Whether packages may be downloaded from `repo` under the current options.
+///
+/// In `--offline` mode only local (`file://`) mirrors are reachable.
+fn can_reach(repo: &str, options: &FetchOptions) -> bool {
+    !options.offline || is_local_url(repo)
+}
+
+/// Resolve the version to fetch when none was pinned.
+///
+/// `resolve` queries the repository; when it is unreachable in `--offline`
+/// mode the newest cached version is used instead.
+fn resolve_version(
+    kind: &str,
+    name: &str,
+    repo: &str,
+    options: &FetchOptions,
+    resolve: impl FnOnce() -> Result<String>,
+) -> Result<String> {
+    if can_reach(repo, options) {
+        return resolve();
     }
 
-    Ok(FetchedPackage {
-        temp_dir,
-        source_path,
-        name: name.to_string(),
-        version,
+    let cached = if options.no_cache {
+        None
+    } else {
+        Cache::open()?.latest(kind, repo, name)
+    };
+
+    cached.map(|entry| entry.version).with_context(|| {
+        format!(
+            "Offline mode: '{name}' is not in the cache and {} is not a local mirror",
+            auth::redact(repo)
+        )
     })
 }
 
-/// Fetch an R package from Bioconductor
-pub fn fetch_bioconductor_package(name: &str) -> Result<FetchedPackage> {
+/// Fetch a package version through the on-disk cache.
+///
+/// A cached copy is reused when present; otherwise `download` extracts the
+/// package from `repo` into a temporary directory and returns its source
+/// root, which is then stored in the cache (unless `--no-cache` was given).
+fn fetch_cached(
+    kind: &str,
+    name: &str,
+    version: &str,
+    repo: &str,
+    options: &FetchOptions,
+    download: impl FnOnce(&Path) -> Result<(PathBuf, Origin)>,
+) -> Result<FetchedPackage> {
+    let cache = if options.no_cache {
+        None
+    } else {
+        Some(Cache::open()?)
+    };
+
+    if let Some(entry) = cache
+        .as_ref()
+        .and_then(|c| c.lookup(kind, repo, name, version))
+    {
+        eprintln!("  → Using cached source ({name} {version})");
+        return Ok(FetchedPackage {
+            temp_dir: None,
+            source_path: entry.source_path(),
+            name: name.to_string(),
+            version: Some(version.to_string()),
+            origin: entry.origin,
+        });
+    }
+
+    if !can_reach(repo, options) {
+        anyhow::bail!(
+            "Offline mode: {name} {version} is not in the cache and {} is not a local mirror",
+            auth::redact(repo)
+        );
+    }
+
     let temp_dir = TempDir::new().context("Failed to create temp directory")?;
-    let destdir = path_to_str(temp_dir.path())?;
-
-    // Use R to download the package via BiocManager
-    let r_script = format!(
-        r#"
-        pkg <- "{name}"
-        destdir <- "{destdir}"
-        
-        if (!requireNamespace("BiocManager", quietly = TRUE)) {{
-            stop("BiocManager must be installed to fetch Bioconductor packages")
-        }}
-        
-        # Ensure CRAN mirror is set
-        options(repos = c(CRAN = "https://cloud.r-project.org"))
-        
-        # Get Bioconductor repositories
-        repos <- BiocManager::repositories()
-        
-        # Get package info
-        available <- available.packages(repos = repos)
-        if (!(pkg %in% rownames(available))) {{
-            stop(paste("Package", pkg, "not found in Bioconductor repositories"))
-        }}
-        
-        version <- available[pkg, "Version"]
-        
-        # Download source tarball using download.packages for reliability
-        # Pass 'available' to avoid re-fetching metadata
-        dl_res <- download.packages(pkg, destdir, available = available, type = "source", quiet = TRUE)
-        
-        if (nrow(dl_res) < 1) {{
-            stop("Failed to download package")
-        }}
-        
-        destfile <- dl_res[1, 2]
-        
-        # Extract
-        untar(destfile, exdir = destdir)
-        
-        # Output info
-        cat("VERSION:", version, "\n")
-        # Usually extract to a directory with the package name
-        cat("PATH:", file.path(destdir, pkg), "\n")
-    "#
-    );
-
-    let output = Command::new("Rscript")
-        .args(["--vanilla", "-e", &r_script])
-        .output()
-        .context("Failed to run Rscript for Bioconductor download")?;
-
-    if !output.status.success() {
-        let stderr = String::from_utf8_lossy(&output.stderr);
-        anyhow::bail!("Failed to download Bioconductor package '{name}': {stderr}");
-    }
-
-    let stdout = String::from_utf8_lossy(&output.stdout);
-    let mut version = None;
-    let mut source_path = temp_dir.path().join(name);
-
-    for line in stdout.lines() {
-        if let Some(v) = line.strip_prefix("VERSION: ") {
-            version = Some(v.trim().to_string());
-        }
-        if let Some(p) = line.strip_prefix("PATH: ") {
-            source_path = PathBuf::from(p.trim());
+    let (source_path, origin) = download(temp_dir.path())?;
+
+    let (temp_dir, source_path) = match cache {
+        Some(cache) => {
+            let entry = cache.store(kind, repo, name, version, &source_path, &origin)?;
+            (None, entry.source_path())
         }
-    }
+        None => (Some(temp_dir), source_path),
+    };
 
     Ok(FetchedPackage {
         temp_dir,
         source_path,
         name: name.to_string(),
-        version,
+        version: Some(version.to_string()),
+        origin,
     })
 }
 
-/// Fetch an R package from GitHub
-pub fn fetch_github_r_package(
-    owner: &str,
-    repo: &str,
-    ref_: Option<&str>,
+/// Extract an archive below `dest_dir` and return the package root.
+///
+/// The root is the single top-level directory of the archive when there is
+/// one (as in CRAN tarballs and sdists), otherwise the extraction directory.
+fn unpack(archive_path: &Path, dest_dir: &Path) -> Result<PathBuf> {
+    let target = dest_dir.join("src");
+    std::fs::create_dir_all(&target)?;
+    archive::extract(archive_path, &target)?;
+    Ok(find_single_directory(&target)?.unwrap_or(target))
+}
+
+/// Fetch an R package from CRAN.
+///
+/// When `version` is given and is not the current release, the tarball is
+/// taken from the CRAN Archive instead.
+pub fn fetch_cran_package(
+    name: &str,
+    version: Option<&str>,
+    options: &FetchOptions,
 ) -> Result<FetchedPackage> {
-    let temp_dir = TempDir::new().context("Failed to create temp directory")?;
-    let ref_name = ref_.unwrap_or("HEAD");
+    let repo = options.cran_repo.trim_end_matches('/');
+    let contrib_url = format!("{repo}/src/contrib");
+
+    let mut listed = None;
+    let version = match version {
+        Some(v) => v.to_string(),
+        None => resolve_version("cran", name, repo, options, || {
+            let entry = find_packages_entry(&contrib_url, name)?.ok_or_else(|| {
+                FetchError::NotFound(format!(
+                    "Package '{name}' not found in {}",
+                    auth::redact(repo)
+                ))
+            })?;
+            let version = entry.get("Version").cloned().with_context(|| {
+                format!(
+                    "No version for '{name}' in {}/PACKAGES",
+                    auth::redact(&contrib_url)
+                )
+            })?;
+            listed = Some(entry);
+            Ok(version)
+        })?,
+    };
+
+    fetch_cached("cran", name, &version, repo, options, |destdir| {
+        // The current release lives in src/contrib, older releases only in the Archive
+        let file_name = format!("{name}_{version}.tar.gz");
+        let sources = [
+            (format!("{contrib_url}/{file_name}"), "cran"),
+            (
+                format!("{contrib_url}/Archive/{name}/{file_name}"),
+                "cran-archive",
+            ),
+        ];
+
+        let tarball = destdir.join(&file_name);
+        let mut errors = Vec::new();
+        for (url, fetch_method) in sources {
+            if let Err(e) = http::download(&url, &tarball) {
+                errors.push(e);
+                continue;
+            }
+
+            // Only the current release is listed (with its MD5sum) in the index
+            let expected = if fetch_method == "cran" {
+                let entry = match listed {
+                    Some(entry) => Some(entry),
+                    None => find_packages_entry(&contrib_url, name).ok().flatten(),
+                };
+                entry
+                    .filter(|e| e.get("Version") == Some(&version))
+                    .and_then(|e| packages_checksum(&e))
+            } else {
+                None
+            };
+            let origin = Origin {
+                source_url: Some(url),
+                sha256: Some(checksum::verify(&tarball, expected.as_ref())?),
+                ..Origin::fetched(fetch_method)
+            };
+            return Ok((unpack(&tarball, destdir)?, origin));
+        }
+
+        // Missing from both locations means the version does not exist;
+        // anything else (e.g. a network failure) is reported as is
+        if errors.iter().all(error::is_not_found) {
+            return Err(FetchError::VersionNotFound {
+                name: name.to_string(),
+                version: version.clone(),
+                repository: auth::redact(repo),
+            }
+            .into());
+        }
+        let error = errors
+            .into_iter()
+            .find(|e| !error::is_not_found(e))
+            .context("No download URL")?;
+        Err(error.context(format!("Failed to download {name} {version}")))
+    })
+}
+
+/// Base URL of the Bioconductor project
+const BIOC_URL: &str = "https://bioconductor.org";
+
+/// Fetch an R package from Bioconductor.
+///
+/// Without a `release`, the package is taken from the current release (or
+/// from `--bioc-repo`). Packages of a pinned release are cached separately,
+/// so that offline runs stay within that release.
+pub fn fetch_bioconductor_package(
+    name: &str,
+    release: Option<&str>,
+    options: &FetchOptions,
+) -> Result<FetchedPackage> {
+    fetch_bioconductor_from(BIOC_URL, name, release, options)
+}
+
+/// Fetch an R package from the Bioconductor project served at `bioc_url`
+fn fetch_bioconductor_from(
+    bioc_url: &str,
+    name: &str,
+    release: Option<&str>,
+    options: &FetchOptions,
+) -> Result<FetchedPackage> {
+    let repo = options
+        .bioc_repo
+        .as_deref()
+        .unwrap_or(bioc_url)
+        .trim_end_matches('/');
+    let kind = match release {
+        Some(release) => format!("bioc-{release}"),
+        None => "bioc".to_string(),
+    };
+
+    let mut resolved = None;
+    let version = resolve_version(&kind, name, repo, options, || {
+        let (entry, url, release) = resolve_bioconductor_package(bioc_url, name, release, options)?;
+        let version = entry.get("Version").cloned().with_context(|| {
+            format!("No version for '{name}' in {}/PACKAGES", auth::redact(&url))
+        })?;
+        resolved = Some((entry, url, release));
+        Ok(version)
+    })?;
+
+    fetch_cached(&kind, name, &version, repo, options, |destdir| {
+        let (entry, contrib_url, release) =
+            resolved.context("Bioconductor repository was not resolved")?;
+        let file_name = format!("{name}_{version}.tar.gz");
+        let tarball = destdir.join(&file_name);
+        let url = format!("{contrib_url}/{file_name}");
+        http::download(&url, &tarball)
+            .with_context(|| format!("Failed to download Bioconductor package '{name}'"))?;
+
+        let origin = Origin {
+            source_url: Some(url),
+            sha256: Some(checksum::verify(
+                &tarball,
+                packages_checksum(&entry).as_ref(),
+            )?),
+            bioconductor_release: release,
+            ..Origin::fetched("bioconductor")
+        };
+        Ok((unpack(&tarball, destdir)?, origin))
+    })
+}
 
-    // Download tarball from GitHub
-    let url = format!("https://github.com/{owner}/{repo}/archive/{ref_name}.tar.gz");
+/// Find a package in the Bioconductor repositories of `release` (by
+/// default the current one).
+///
+/// Returns its index entry, the `src/contrib` URL of the repository holding
+/// it and the release, when known. A `--bioc-repo` is taken to serve the
+/// requested release.
+fn resolve_bioconductor_package(
+    bioc_url: &str,
+    name: &str,
+    release: Option<&str>,
+    options: &FetchOptions,
+) -> Result<(dcf::Paragraph, String, Option<String>)> {
+    let (contrib_urls, release) = match &options.bioc_repo {
+        Some(repo) => (
+            vec![format!("{}/src/contrib", repo.trim_end_matches('/'))],
+            release.map(str::to_string),
+        ),
+        None => {
+            let release = match release {
+                Some(release) => release.to_string(),
+                None => current_bioconductor_release(bioc_url)?,
+            };
+            // Same repositories as BiocManager::repositories(), minus CRAN
+            let urls = ["bioc", "data/annotation", "data/experiment", "workflows"]
+                .iter()
+                .map(|r| format!("{bioc_url}/packages/{release}/{r}/src/contrib"))
+                .collect();
+            (urls, Some(release))
+        }
+    };
+
+    for contrib_url in contrib_urls {
+        let entry = match find_packages_entry(&contrib_url, name) {
+            Ok(entry) => entry,
+            // Old releases lack some of the repositories
+            Err(e) if error::is_not_found(&e) => None,
+            Err(e) => return Err(e),
+        };
+        if let Some(entry) = entry {
+            return Ok((entry, contrib_url, release));
+        }
+    }
 
-    let tarball_path = temp_dir.path().join("source.tar.gz");
-    let tarball_str = path_to_str(&tarball_path)?;
-    let temp_dir_str = path_to_str(temp_dir.path())?;
+    let in_release = release.map_or(String::new(), |r| format!(" of release {r}"));
+    Err(FetchError::NotFound(format!(
+        "Package '{name}' not found in Bioconductor repositories{in_release}"
+    ))
+    .into())
+}
 
-    // Use curl to download
-    let output = Command::new("curl")
-        .args(["-sL", "-o", tarball_str, &url])
-        .output()
-        .context("Failed to download from GitHub")?;
+/// Look up the current Bioconductor release (e.g. "3.20")
+fn current_bioconductor_release(bioc_url: &str) -> Result<String> {
+    let config: serde_yaml::Value =
+        serde_yaml::from_str(&http::get_string(&format!("{bioc_url}/config.yaml"))?)
+            .context("Failed to parse Bioconductor config.yaml")?;
+
+    config
+        .get("release_version")
+        .and_then(serde_yaml::Value::as_str)
+        .map(ToString::to_string)
+        .context("Bioconductor config.yaml has no release_version")
+}
 
-    if !output.status.success() {
-        anyhow::bail!("Failed to download from GitHub: {url}");
+/// Read the `PACKAGES` index of a CRAN-like repository's `src/contrib`
+fn read_packages_index(contrib_url: &str) -> Result<Vec<dcf::Paragraph>> {
+    let content = match http::get(&format!("{contrib_url}/PACKAGES.gz")) {
+        Ok(compressed) => {
+            let mut content = String::new();
+            flate2::read::GzDecoder::new(compressed.as_slice())
+                .read_to_string(&mut content)
+                .with_context(|| {
+                    format!("Corrupt PACKAGES.gz index in {}", auth::redact(contrib_url))
+                })?;
+            Ok(content)
+        }
+        // Not every mirror publishes the compressed index
+        Err(e) if error::is_not_found(&e) => http::get_string(&format!("{contrib_url}/PACKAGES")),
+        Err(e) => Err(e),
     }
+    .with_context(|| {
+        format!(
+            "Failed to read the package index of {}",
+            auth::redact(contrib_url)
+        )
+    })?;
+
+    Ok(dcf::parse(&content))
+}
+
+/// Find the entry of a package in a repository's `PACKAGES` index
+fn find_packages_entry(contrib_url: &str, name: &str) -> Result<Option<dcf::Paragraph>> {
+    Ok(read_packages_index(contrib_url)?
+        .into_iter()
+        .find(|p| p.get("Package").is_some_and(|n| n == name)))
+}
 
-    // Extract tarball
-    let output = Command::new("tar")
-        .args(["-xzf", tarball_str, "-C", temp_dir_str])
-        .output()
-        .context("Failed to extract tarball")?;
+/// Checksum of a package tarball as published in its `PACKAGES` entry
+fn packages_checksum(entry: &dcf::Paragraph) -> Option<Checksum> {
+    entry.get("MD5sum").map(|md5| Checksum::Md5(md5.clone()))
+}
 
-    if !output.status.success() {
-        anyhow::bail!("Failed to extract tarball");
+/// Fetch an R package from an r-universe (`https://{owner}.r-universe.dev`).
+///
+/// Universes are CRAN-like repositories built from git; their `PACKAGES`
+/// index records the upstream commit of each package as `RemoteSha`.
+pub fn fetch_runiverse_package(
+    owner: &str,
+    name: &str,
+    options: &FetchOptions,
+) -> Result<FetchedPackage> {
+    fetch_universe_package(
+        &format!("https://{owner}.r-universe.dev"),
+        owner,
+        name,
+        options,
+    )
+}
+
+/// Fetch an R package from the universe of `owner` served at `repo`
+fn fetch_universe_package(
+    repo: &str,
+    owner: &str,
+    name: &str,
+    options: &FetchOptions,
+) -> Result<FetchedPackage> {
+    let contrib_url = format!("{repo}/src/contrib");
+    let kind = format!("runiverse/{owner}");
+
+    let mut listed = None;
+    let version = resolve_version(&kind, name, repo, options, || {
+        let entry = find_packages_entry(&contrib_url, name)?.ok_or_else(|| {
+            FetchError::NotFound(format!(
+                "Package '{name}' not found in the {owner} universe"
+            ))
+        })?;
+        let version = entry.get("Version").cloned().with_context(|| {
+            format!(
+                "No version for '{name}' in {}/PACKAGES",
+                auth::redact(&contrib_url)
+            )
+        })?;
+        listed = Some(entry);
+        Ok(version)
+    })?;
+
+    let package = fetch_cached(&kind, name, &version, repo, options, |destdir| {
+        let file_name = format!("{name}_{version}.tar.gz");
+        let tarball = destdir.join(&file_name);
+        let url = format!("{contrib_url}/{file_name}");
+        http::download(&url, &tarball)
+            .with_context(|| format!("Failed to download '{name}' from the {owner} universe"))?;
+
+        let mut entry = listed.unwrap_or_default();
+        let origin = Origin {
+            sha256: Some(checksum::verify(
+                &tarball,
+                packages_checksum(&entry).as_ref(),
+            )?),
+            commit: entry.remove("RemoteSha"),
+            source_url: Some(url),
+            ..Origin::fetched("r-universe")
+        };
+        Ok((unpack(&tarball, destdir)?, origin))
+    })?;
+
+    if options.expect_sha.is_some() {
+        let commit = package.origin.commit.as_deref().with_context(|| {
+            format!("The {owner} universe does not record a commit for '{name}'")
+        })?;
+        check_expected_commit(commit, options)?;
     }
+    Ok(package)
+}
 
-    // Find the extracted directory (usually repo-ref/)
-    let source_path =
-        find_single_directory(temp_dir.path())?.unwrap_or_else(|| temp_dir.path().to_path_buf());
+/// Fetch an R package from a git repository.
+///
+/// The package is looked for in `subdir` (or the repository root) and, if
+/// there is no DESCRIPTION there, in the directories below it.
+pub fn fetch_repo_r_package(
+    repository: &Repository,
+    subdir: Option<&str>,
+    ref_: Option<&str>,
+    options: &FetchOptions,
+) -> Result<FetchedPackage> {
+    let (temp_dir, source_path, origin) =
+        fetch_repo_source(repository, subdir, ref_, R_PACKAGE_MARKERS, options)?;
 
-    // Try to get version from DESCRIPTION
+    let name = parse_description_name(&source_path).unwrap_or_else(|| repository.default_name());
     let version = parse_description_version(&source_path);
 
     Ok(FetchedPackage {
         temp_dir,
         source_path,
-        name: repo.to_string(),
+        name,
         version,
+        origin,
     })
 }
 
-/// Fetch a Python package from `PyPI`
-pub fn fetch_pypi_package(name: &str) -> Result<FetchedPackage> {
-    let temp_dir = TempDir::new().context("Failed to create temp directory")?;
-    let temp_dir_str = path_to_str(temp_dir.path())?;
-
-    // Use pip to download source
-    let output = Command::new("python3")
-        .args([
-            "-m",
-            "pip",
-            "download",
-            "--no-binary",
-            ":all:",
-            "--no-deps",
-            "-d",
-            temp_dir_str,
-            name,
-        ])
-        .output()
-        .context("Failed to download from PyPI")?;
-
-    if !output.status.success() {
-        let stderr = String::from_utf8_lossy(&output.stderr);
-        anyhow::bail!("Failed to download PyPI package '{name}': {stderr}");
-    }
-
-    // Find the downloaded file and extract it
-    let entries: Vec<_> = std::fs::read_dir(temp_dir.path())?
-        .filter_map(Result::ok)
-        .collect();
+/// Fetch a snapshot of a repository and locate the package inside it.
+///
+/// Snapshots of forges and git remotes are cached by commit. A ref that is
+/// a full commit SHA is served from the cache without any network access;
+/// other refs are resolved first.
+fn fetch_repo_source(
+    repository: &Repository,
+    subdir: Option<&str>,
+    ref_: Option<&str>,
+    markers: &[&str],
+    options: &FetchOptions,
+) -> Result<(Option<TempDir>, PathBuf, Origin)> {
+    let name = repository.default_name();
+    let pinned = ref_
+        .filter(|r| is_commit_sha(r))
+        .map(str::to_ascii_lowercase);
+
+    let snapshot = match repository {
+        Repository::Forge { forge, owner, repo } => {
+            // Download by commit rather than by ref, so that the recorded SHA
+            // is exactly what was extracted
+            let commit = match pinned {
+                Some(commit) => commit,
+                None if options.offline => anyhow::bail!(
+                    "Offline mode: cannot resolve {} of {repository} (pin a full commit SHA)",
+                    ref_.unwrap_or("the default branch")
+                ),
+                None => forge.resolve_commit(owner, repo, ref_, options)?,
+            };
+            check_expected_commit(&commit, options)?;
+
+            let web_url = forge.web_url(owner, repo, options);
+            fetch_cached(
+                forge.prefix(),
+                &name,
+                &commit,
+                &web_url,
+                options,
+                |destdir| {
+                    let url = forge.archive_url(owner, repo, &commit, options);
+                    let tarball = destdir.join("source.tar.gz");
+                    http::download(&url, &tarball)
+                        .with_context(|| format!("Failed to download {repository}: {url}"))?;
+
+                    let origin = Origin {
+                        commit: Some(commit.clone()),
+                        sha256: Some(checksum::verify(&tarball, None)?),
+                        source_url: Some(url),
+                        ..Origin::fetched(forge.prefix())
+                    };
+
+                    // The archive holds a single repo-sha/ directory
+                    Ok((unpack(&tarball, destdir)?, origin))
+                },
+            )?
+        }
+        Repository::Remote(url) => {
+            let scratch = TempDir::new().context("Failed to create temp directory")?;
+            let git_dir = scratch.path().join("repo.git");
+            let commit = match pinned {
+                Some(commit) => commit,
+                None if !can_reach(url, options) => {
+                    anyhow::bail!("Offline mode: cannot fetch {repository}")
+                }
+                None => git::fetch(url, ref_, &git_dir)?,
+            };
+            check_expected_commit(&commit, options)?;
+
+            fetch_cached("git", &name, &commit, url, options, |destdir| {
+                if !git_dir.exists() {
+                    let fetched = git::fetch(url, Some(&commit), &git_dir)?;
+                    if fetched != commit {
+                        anyhow::bail!("Fetched commit {fetched} instead of {commit}");
+                    }
+                }
+                let (root, sha256) = unpack_commit(&git_dir, &commit, destdir)?;
+                let origin = Origin {
+                    commit: Some(commit.clone()),
+                    sha256: Some(sha256),
+                    source_url: Some(url.clone()),
+                    ..Origin::fetched("git")
+                };
+                Ok((root, origin))
+            })?
+        }
+        Repository::Local(path) => {
+            // Read straight from the local object database, no cache needed
+            let temp_dir = TempDir::new().context("Failed to create temp directory")?;
+            let git_dir = git::git_dir(path)?;
+            let commit = git::resolve_commit(&git_dir, ref_.unwrap_or("HEAD"))?;
+            check_expected_commit(&commit, options)?;
+            let (root, sha256) = unpack_commit(&git_dir, &commit, temp_dir.path())?;
+            let origin = Origin {
+                commit: Some(commit),
+                sha256: Some(sha256),
+                source_url: file_url(path),
+                ..Origin::fetched("git")
+            };
+            FetchedPackage {
+                temp_dir: Some(temp_dir),
+                source_path: root,
+                name,
+                version: None,
+                origin,
+            }
+        }
+    };
 
-    if entries.is_empty() {
-        anyhow::bail!("No files downloaded for package '{name}'");
+    let source_path = locate_package_dir(&snapshot.source_path, subdir, markers)?;
+    Ok((snapshot.temp_dir, source_path, snapshot.origin))
+}
+
+/// Fail when `--expect-sha` was given and `commit` does not match it.
+///
+/// Abbreviated SHAs are accepted as long as they are a prefix of the commit.
+fn check_expected_commit(commit: &str, options: &FetchOptions) -> Result<()> {
+    let Some(expected) = options.expect_sha.as_deref() else {
+        return Ok(());
+    };
+    let expected = expected.trim().to_ascii_lowercase();
+    if expected.len() < 7 || !commit.to_ascii_lowercase().starts_with(&expected) {
+        anyhow::bail!("Resolved commit {commit} does not match the expected SHA {expected}");
     }
+    Ok(())
+}
 
-    let archive_path = entries[0].path();
-    extract_archive(&archive_path, temp_dir.path())?;
+/// Extract the tree of a commit into `dest_dir`, returning its root and the
+/// SHA-256 of the intermediate tarball
+fn unpack_commit(git_dir: &Path, commit: &str, dest_dir: &Path) -> Result<(PathBuf, String)> {
+    let tarball = dest_dir.join("source.tar");
+    git::archive(git_dir, commit, "repo", &tarball)?;
+    let sha256 = checksum::verify(&tarball, None)?;
+    Ok((unpack(&tarball, dest_dir)?, sha256))
+}
 
-    // Find the extracted directory
-    let source_path =
-        find_single_directory(temp_dir.path())?.unwrap_or_else(|| temp_dir.path().to_path_buf());
+/// Resolve the package directory inside an unpacked repository.
+///
+/// Starts at `subdir` (or the root) and, when no marker file is found there,
+/// searches the directories below it. Falls back to the starting directory
+/// so that the extractor can report what is missing.
+fn locate_package_dir(repo_root: &Path, subdir: Option<&str>, markers: &[&str]) -> Result<PathBuf> {
+    let start = match subdir {
+        Some(subdir) => {
+            let dir = repo_root.join(subdir);
+            if !dir.is_dir() {
+                anyhow::bail!("Subdirectory '{subdir}' not found in repository");
+            }
+            dir
+        }
+        None => repo_root.to_path_buf(),
+    };
+
+    match find_package_root(&start, markers) {
+        Some(found) if found != start => {
+            let relative = found.strip_prefix(repo_root).unwrap_or(&found);
+            eprintln!("  → Found package in {}/", relative.display());
+            Ok(found)
+        }
+        _ => Ok(start),
+    }
+}
 
-    // Parse version from directory name (usually package-version/)
-    let version = source_path
-        .file_name()
-        .and_then(|n| n.to_str())
-        .and_then(|n| n.strip_prefix(&format!("{name}-")))
-        .map(ToString::to_string);
+/// Fetch a Python package from `PyPI`, optionally pinned to an exact release.
+///
+/// The sdist is preferred. Releases without one fall back to a pure-Python
+/// wheel and then to the Python sources of a platform wheel.
+pub fn fetch_pypi_package(
+    name: &str,
+    version: Option<&str>,
+    options: &FetchOptions,
+) -> Result<FetchedPackage> {
+    let index = options
+        .pypi_index
+        .as_deref()
+        .or(auth::PipConfig::get().index_url.as_deref())
+        .unwrap_or("https://pypi.org/simple")
+        .trim_end_matches('/');
+
+    let mut listing = None;
+    let version = match version {
+        Some(v) => v.to_string(),
+        None => resolve_version("pypi", name, index, options, || {
+            let files = list_index_files(index, name)?;
+            let version = latest_release_version(&files).with_context(|| {
+                format!(
+                    "No distribution files for '{name}' on {}",
+                    auth::redact(index)
+                )
+            })?;
+            listing = Some(files);
+            Ok(version)
+        })?,
+    };
+
+    fetch_cached("pypi", name, &version, index, options, |destdir| {
+        let files = match listing {
+            Some(files) => files,
+            None => list_index_files(index, name)?,
+        };
+        let file = files
+            .iter()
+            .filter(|f| f.version == version)
+            .min_by(|a, b| (a.artifact, &a.filename).cmp(&(b.artifact, &b.filename)))
+            .ok_or_else(|| FetchError::VersionNotFound {
+                name: name.to_string(),
+                version: version.clone(),
+                repository: auth::redact(index),
+            })?;
+
+        if file.artifact != Artifact::Sdist {
+            eprintln!("  → No sdist for {name} {version}, using {}", file.filename);
+        }
 
-    Ok(FetchedPackage {
-        temp_dir,
-        source_path,
-        name: name.to_string(),
-        version,
+        let archive_path = destdir.join(&file.filename);
+        http::download(&file.url, &archive_path)
+            .with_context(|| format!("Failed to download {}", file.filename))?;
+        let sha256 = checksum::verify(&archive_path, file.checksum.as_ref())?;
+
+        let source_path = match file.artifact {
+            Artifact::Sdist | Artifact::PureWheel => unpack(&archive_path, destdir)?,
+            Artifact::PlatformWheel => {
+                // Compiled extensions are of no use here: keep only Python
+                // sources, type stubs and the distribution metadata
+                let target = destdir.join("src");
+                std::fs::create_dir_all(&target)?;
+                archive::extract_filtered(&archive_path, &target, |member| {
+                    member.extension().is_some_and(|e| e == "py" || e == "pyi")
+                        || member.ends_with("METADATA")
+                })?;
+                target
+            }
+        };
+
+        let origin = Origin {
+            artifact: Some(file.artifact.as_str().to_string()),
+            source_url: Some(file.url.clone()),
+            sha256: Some(sha256),
+            ..Origin::fetched("pypi")
+        };
+        Ok((source_path, origin))
     })
 }
 
-/// Fetch a Python package from GitHub
-pub fn fetch_github_python_package(
-    owner: &str,
-    repo: &str,
+/// Kind of distribution file on a Python package index, in order of preference
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Artifact {
+    /// Source distribution
+    Sdist,
+    /// Wheel that runs anywhere (`*-none-any.whl`)
+    PureWheel,
+    /// Wheel built for a specific interpreter, ABI or platform
+    PlatformWheel,
+}
+
+impl Artifact {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Sdist => "sdist",
+            Self::PureWheel => "wheel",
+            Self::PlatformWheel => "platform-wheel",
+        }
+    }
+}
+
+/// A distribution file listed on a Python package index
+struct IndexFile {
+    /// File name (e.g. "requests-2.31.0.tar.gz")
+    filename: String,
+    /// Absolute download URL
+    url: String,
+    /// Version parsed from the file name
+    version: String,
+    /// Kind of distribution
+    artifact: Artifact,
+    /// Digest published with the link (`#sha256=...`)
+    checksum: Option<Checksum>,
+    /// Whether the release was yanked (PEP 592)
+    yanked: bool,
+}
+
+/// List the sdists and wheels of a package on a simple (PEP 503) index
+fn list_index_files(index: &str, name: &str) -> Result<Vec<IndexFile>> {
+    let page_url = format!("{index}/{}/", normalize_python_name(name));
+    let page = match http::get_string(&page_url) {
+        Ok(page) => page,
+        Err(e) if error::is_not_found(&e) => {
+            return Err(FetchError::NotFound(format!(
+                "Package '{name}' not found on {}",
+                auth::redact(index)
+            ))
+            .into())
+        }
+        Err(e) => {
+            return Err(e.context(format!(
+                "Failed to list the files of '{name}' on {}",
+                auth::redact(index)
+            )))
+        }
+    };
+    let base = url::Url::parse(&page_url)
+        .with_context(|| format!("Invalid index URL: {}", auth::redact(index)))?;
+
+    let anchor = regex::Regex::new(r#"(?is)<a\s([^>]*)>(.*?)</a>"#).expect("valid regex");
+    let href = regex::Regex::new(r#"(?i)href\s*=\s*["']([^"']+)["']"#).expect("valid regex");
+
+    let mut files = Vec::new();
+    for caps in anchor.captures_iter(&page) {
+        let attributes = &caps[1];
+        let filename = caps[2].trim().to_string();
+        let Some((version, artifact)) = distribution_file_info(&filename, name) else {
+            continue;
+        };
+        let Some(link) = href.captures(attributes) else {
+            continue;
+        };
+        let Ok(mut url) = base.join(&link[1].replace("&amp;", "&")) else {
+            continue;
+        };
+        let checksum = url
+            .fragment()
+            .and_then(|f| f.strip_prefix("sha256="))
+            .map(|hash| Checksum::Sha256(hash.to_string()));
+        url.set_fragment(None);
+
+        files.push(IndexFile {
+            filename,
+            url: url.to_string(),
+            version,
+            artifact,
+            checksum,
+            yanked: attributes.contains("data-yanked"),
+        });
+    }
+
+    Ok(files)
+}
+
+/// Pick the newest non-yanked version, preferring final releases
+fn latest_release_version(files: &[IndexFile]) -> Option<String> {
+    let candidates: Vec<&IndexFile> = files.iter().filter(|f| !f.yanked).collect();
+
+    let newest = |only_final: bool| {
+        candidates
+            .iter()
+            .filter(|f| !only_final || is_final_version(&f.version))
+            .max_by(|a, b| compare_versions(&a.version, &b.version))
+            .map(|f| f.version.clone())
+    };
+
+    newest(true).or_else(|| newest(false))
+}
+
+/// Parse the version and kind of a distribution file name such as
+/// `python-dateutil-2.8.2.tar.gz` or `numpy-1.26.4-cp312-cp312-win_amd64.whl`
+fn distribution_file_info(filename: &str, name: &str) -> Option<(String, Artifact)> {
+    if let Some(stem) = filename.strip_suffix(".whl") {
+        // {name}-{version}(-{build})?-{python}-{abi}-{platform}.whl
+        let parts: Vec<&str> = stem.split('-').collect();
+        if !(5..=6).contains(&parts.len())
+            || normalize_python_name(parts[0]) != normalize_python_name(name)
+        {
+            return None;
+        }
+        let artifact = match parts[parts.len() - 2..] {
+            ["none", "any"] => Artifact::PureWheel,
+            _ => Artifact::PlatformWheel,
+        };
+        return Some((parts[1].to_string(), artifact));
+    }
+
+    let stem = [".tar.gz", ".zip", ".tar.bz2", ".tgz"]
+        .iter()
+        .find_map(|ext| filename.strip_suffix(ext))?;
+    let (dist_name, version) = stem.rsplit_once('-')?;
+
+    (normalize_python_name(dist_name) == normalize_python_name(name) && !version.is_empty())
+        .then(|| (version.to_string(), Artifact::Sdist))
+}
+
+/// Normalize a Python distribution name (PEP 503)
+fn normalize_python_name(name: &str) -> String {
+    let mut normalized = String::with_capacity(name.len());
+    let mut last_was_separator = false;
+    for c in name.chars() {
+        if matches!(c, '-' | '_' | '.') {
+            if !last_was_separator {
+                normalized.push('-');
+            }
+            last_was_separator = true;
+        } else {
+            normalized.push(c.to_ascii_lowercase());
+            last_was_separator = false;
+        }
+    }
+    normalized
+}
+
+/// Fetch a Python package from a git repository, optionally from a subdirectory
+pub fn fetch_repo_python_package(
+    repository: &Repository,
+    subdir: Option<&str>,
     ref_: Option<&str>,
+    options: &FetchOptions,
 ) -> Result<FetchedPackage> {
-    // Same as R GitHub fetch
-    fetch_github_r_package(owner, repo, ref_)
+    let (temp_dir, source_path, origin) =
+        fetch_repo_source(repository, subdir, ref_, PYTHON_PACKAGE_MARKERS, options)?;
+
+    let (name, version) = parse_python_package_info(&source_path);
+
+    Ok(FetchedPackage {
+        temp_dir,
+        source_path,
+        name: name.unwrap_or_else(|| repository.default_name()),
+        version,
+        origin,
+    })
 }
 
 /// Load a local R package from the filesystem
@@ -438,6 +1613,10 @@ pub fn fetch_local_r_package(path: &Path) -> Result<LocalPackage> {
         source_path: path.to_path_buf(),
         name,
         version,
+        origin: Origin {
+            source_url: file_url(path),
+            ..Origin::fetched("local")
+        },
     })
 }
 
@@ -477,13 +1656,158 @@ pub fn fetch_local_python_package(path: &Path) -> Result<LocalPackage> {
         source_path: path.to_path_buf(),
         name,
         version,
+        origin: Origin {
+            source_url: file_url(path),
+            ..Origin::fetched("local")
+        },
     })
 }
 
+/// Load an R package from a local source tarball (e.g. built by `R CMD build`)
+pub fn fetch_r_archive(path: &Path) -> Result<FetchedPackage> {
+    let (temp_dir, extracted, origin) = extract_local_archive(path)?;
+
+    let source_path = find_package_root(&extracted, R_PACKAGE_MARKERS).with_context(|| {
+        format!(
+            "Not a valid R package archive: no DESCRIPTION file in {}",
+            path.display()
+        )
+    })?;
+
+    let name = parse_description_name(&source_path).unwrap_or_else(|| {
+        archive_stem(path)
+            .split('_')
+            .next()
+            .unwrap_or("unknown")
+            .to_string()
+    });
+    let version = parse_description_version(&source_path);
+
+    Ok(FetchedPackage {
+        temp_dir: Some(temp_dir),
+        source_path,
+        name,
+        version,
+        origin,
+    })
+}
+
+/// Load a Python package from a local sdist or wheel
+pub fn fetch_python_archive(path: &Path) -> Result<FetchedPackage> {
+    let (temp_dir, extracted, origin) = extract_local_archive(path)?;
+
+    // Wheels unpack their modules next to a *.dist-info directory; sdists
+    // hold a single project directory
+    let source_path = if python_metadata::core_metadata_file(&extracted).is_some() {
+        extracted
+    } else {
+        find_package_root(&extracted, PYTHON_PACKAGE_MARKERS).with_context(|| {
+            format!(
+                "Not a valid Python package archive: no PKG-INFO, pyproject.toml or setup.py in {}",
+                path.display()
+            )
+        })?
+    };
+
+    let (name, version) = parse_python_package_info(&source_path);
+    let name = name.unwrap_or_else(|| {
+        archive_stem(path)
+            .split('-')
+            .next()
+            .unwrap_or("unknown")
+            .to_string()
+    });
+
+    let is_wheel = path.extension().is_some_and(|e| e == "whl");
+    let artifact = if is_wheel { "wheel" } else { "sdist" };
+
+    Ok(FetchedPackage {
+        temp_dir: Some(temp_dir),
+        source_path,
+        name,
+        version,
+        origin: Origin {
+            artifact: Some(artifact.to_string()),
+            ..origin
+        },
+    })
+}
+
+/// Extract a local archive into a fresh temporary directory
+fn extract_local_archive(path: &Path) -> Result<(TempDir, PathBuf, Origin)> {
+    let temp_dir = TempDir::new().context("Failed to create temp directory")?;
+    let extracted = temp_dir.path().join("src");
+    std::fs::create_dir_all(&extracted)?;
+
+    let origin = Origin {
+        source_url: file_url(path),
+        sha256: Some(checksum::verify(path, None)?),
+        ..Origin::fetched("local-archive")
+    };
+    archive::extract(path, &extracted)?;
+    Ok((temp_dir, extracted, origin))
+}
+
+/// `file://` URL of a local path
+fn file_url(path: &Path) -> Option<String> {
+    url::Url::from_file_path(path).ok().map(String::from)
+}
+
+/// File name of an archive without its extension(s)
+fn archive_stem(path: &Path) -> &str {
+    let file_name = path.file_name().and_then(|n| n.to_str()).unwrap_or("");
+    [
+        ".tar.gz", ".tar.bz2", ".tgz", ".tbz2", ".tar", ".zip", ".whl",
+    ]
+    .iter()
+    .find_map(|ext| file_name.strip_suffix(ext))
+    .unwrap_or(file_name)
+}
+
 // ============================================================================
 // Helper functions
 // ============================================================================
 
+/// Files that mark the root of an R package
+const R_PACKAGE_MARKERS: &[&str] = &["DESCRIPTION"];
+
+/// Files that mark the root of a Python project
+const PYTHON_PACKAGE_MARKERS: &[&str] = &["PKG-INFO", "pyproject.toml", "setup.py", "setup.cfg"];
+
+/// How deep below an extracted archive to look for the package root
+const MAX_ROOT_DEPTH: usize = 3;
+
+/// Find the shallowest directory under `path` containing one of `markers`
+fn find_package_root(path: &Path, markers: &[&str]) -> Option<PathBuf> {
+    let mut level = vec![path.to_path_buf()];
+
+    for _ in 0..=MAX_ROOT_DEPTH {
+        let mut candidates: Vec<PathBuf> = level
+            .iter()
+            .filter(|dir| markers.iter().any(|m| dir.join(m).is_file()))
+            .cloned()
+            .collect();
+        if !candidates.is_empty() {
+            candidates.sort();
+            return candidates.into_iter().next();
+        }
+
+        level = level
+            .iter()
+            .filter_map(|dir| std::fs::read_dir(dir).ok())
+            .flat_map(|entries| entries.filter_map(Result::ok).map(|e| e.path()))
+            .filter(|p| p.is_dir())
+            .filter(|p| {
+                !p.file_name()
+                    .and_then(|n| n.to_str())
+                    .is_some_and(|n| n.starts_with('.'))
+            })
+            .collect();
+    }
+
+    None
+}
+
 /// Find a single directory in the given path, used for finding extracted archives
 fn find_single_directory(path: &Path) -> Result<Option<PathBuf>> {
     let directories: Vec<_> = std::fs::read_dir(path)?
@@ -498,127 +1822,607 @@ fn find_single_directory(path: &Path) -> Result<Option<PathBuf>> {
     })
 }
 
-/// Extract an archive (tar.gz, tgz, or zip) to the given directory
-fn extract_archive(archive_path: &Path, dest_dir: &Path) -> Result<()> {
-    let archive_str = path_to_str(archive_path)?;
-    let dest_str = path_to_str(dest_dir)?;
-
-    let extension = archive_path
-        .extension()
-        .and_then(|e| e.to_str())
-        .unwrap_or("");
-
-    let file_name = archive_path
-        .file_name()
-        .and_then(|n| n.to_str())
-        .unwrap_or("");
-
-    if extension == "gz" || extension == "tgz" || file_name.ends_with(".tar.gz") {
-        Command::new("tar")
-            .args(["-xzf", archive_str, "-C", dest_str])
-            .output()
-            .context("Failed to extract tar.gz archive")?;
-    } else if extension == "zip" {
-        Command::new("unzip")
-            .args(["-q", archive_str, "-d", dest_str])
-            .output()
-            .context("Failed to extract zip archive")?;
-    }
-
-    Ok(())
-}
-
 /// Parse version from R DESCRIPTION file
 fn parse_description_version(path: &Path) -> Option<String> {
-    let desc_path = path.join("DESCRIPTION");
-    let content = std::fs::read_to_string(&desc_path).ok()?;
-
-    content
-        .lines()
-        .find_map(|line| line.strip_prefix("Version:"))
-        .map(|v| v.trim().to_string())
+    dcf::read(&path.join("DESCRIPTION")).ok()?.remove("Version")
 }
 
 /// Parse package name from R DESCRIPTION file
 fn parse_description_name(path: &Path) -> Option<String> {
-    let desc_path = path.join("DESCRIPTION");
-    let content = std::fs::read_to_string(&desc_path).ok()?;
-
-    content
-        .lines()
-        .find_map(|line| line.strip_prefix("Package:"))
-        .map(|n| n.trim().to_string())
+    dcf::read(&path.join("DESCRIPTION")).ok()?.remove("Package")
 }
 
-/// Parse package name/version from Python pyproject.toml or setup.py
+/// Parse package name/version from Python project metadata
 fn parse_python_package_info(path: &Path) -> (Option<String>, Option<String>) {
-    // Try pyproject.toml first
-    if let Some(result) = parse_pyproject_toml(path) {
-        return result;
+    let metadata = python_metadata::read(path);
+    (metadata.name, metadata.version)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+
+    #[test]
+    fn test_parse_pinned_versions() {
+        match PackageSource::parse("dplyr@1.0.10", "r").unwrap() {
+            PackageSource::Cran { name, version } => {
+                assert_eq!(name, "dplyr");
+                assert_eq!(version.as_deref(), Some("1.0.10"));
+            }
+            other => panic!("Expected CRAN source, got {other:?}"),
+        }
+
+        match PackageSource::parse("requests==2.31.0", "python").unwrap() {
+            PackageSource::PyPI { name, version } => {
+                assert_eq!(name, "requests");
+                assert_eq!(version.as_deref(), Some("2.31.0"));
+            }
+            other => panic!("Expected PyPI source, got {other:?}"),
+        }
+
+        assert!(PackageSource::parse("dplyr@", "r").is_err());
     }
 
-    // Fallback to setup.py
-    parse_setup_py(path).unwrap_or((None, None))
-}
+    #[test]
+    fn test_rejects_hostile_specs() {
+        let r_specs = [
+            "foo;rm -rf /",
+            "dplyr'); system('x",
+            "dplyr@1.0'); system('x",
+            "bioc:limma\"); q(",
+            "runiverse:../etc/passwd",
+            "github:tidyverse/dplyr@--upload-pack=x",
+            "git+https://example.org/repo.git@-x",
+        ];
+        for spec in r_specs {
+            assert!(PackageSource::parse(spec, "r").is_err(), "{spec}");
+        }
 
-fn parse_pyproject_toml(path: &Path) -> Option<(Option<String>, Option<String>)> {
-    let content = std::fs::read_to_string(path.join("pyproject.toml")).ok()?;
+        let python_specs = ["requests\"; import os", "requests==1.0; id", "a b"];
+        for spec in python_specs {
+            assert!(PackageSource::parse(spec, "python").is_err(), "{spec}");
+        }
 
-    let mut name = None;
-    let mut version = None;
+        // Legitimate names that look unusual still parse
+        assert!(PackageSource::parse("data.table", "r").is_ok());
+        assert!(PackageSource::parse("zope.interface==6.0", "python").is_ok());
+        assert!(PackageSource::parse("github:owner/repo@release/v1.2", "r").is_ok());
+    }
 
-    for line in content.lines() {
-        let line = line.trim();
-        if line.starts_with("name") {
-            name = name.or_else(|| extract_toml_string_value(line));
-        } else if line.starts_with("version") {
-            version = version.or_else(|| extract_toml_string_value(line));
+    #[test]
+    fn test_parse_repository_specs() {
+        match PackageSource::parse("github:apache/arrow/r@apache-arrow-15.0.0", "r").unwrap() {
+            PackageSource::Repository {
+                repository: Repository::Forge { forge, owner, repo },
+                subdir,
+                ref_,
+            } => {
+                assert_eq!(forge, Forge::GitHub);
+                assert_eq!((owner.as_str(), repo.as_str()), ("apache", "arrow"));
+                assert_eq!(subdir.as_deref(), Some("r"));
+                assert_eq!(ref_.as_deref(), Some("apache-arrow-15.0.0"));
+            }
+            other => panic!("Expected GitHub source, got {other:?}"),
+        }
+
+        match PackageSource::parse("codeberg:org/mono/python/pkg", "python").unwrap() {
+            PackageSource::Repository {
+                repository: Repository::Forge { forge, .. },
+                subdir,
+                ref_,
+            } => {
+                assert_eq!(forge, Forge::Codeberg);
+                assert_eq!(subdir.as_deref(), Some("python/pkg"));
+                assert!(ref_.is_none());
+            }
+            other => panic!("Expected Codeberg source, got {other:?}"),
         }
+
+        let spec = "git+https://user@git.example.com/group/pkg.git@v1.2#subdirectory=r";
+        match PackageSource::parse(spec, "r").unwrap() {
+            PackageSource::Repository {
+                repository: Repository::Remote(url),
+                subdir,
+                ref_,
+            } => {
+                assert_eq!(url, "https://user@git.example.com/group/pkg.git");
+                assert_eq!(subdir.as_deref(), Some("r"));
+                assert_eq!(ref_.as_deref(), Some("v1.2"));
+            }
+            other => panic!("Expected git remote source, got {other:?}"),
+        }
+
+        match PackageSource::parse("runiverse:ropensci/rix", "r").unwrap() {
+            PackageSource::RUniverse { owner, name } => {
+                assert_eq!((owner.as_str(), name.as_str()), ("ropensci", "rix"));
+            }
+            other => panic!("Expected r-universe source, got {other:?}"),
+        }
+
+        assert!(PackageSource::parse("runiverse:rix", "r").is_err());
+        assert!(PackageSource::parse("github:owner", "r").is_err());
+        assert!(PackageSource::parse("gitlab:owner/repo/../etc", "r").is_err());
+        assert!(PackageSource::parse("git+https://h/r.git#subdirectory=..", "r").is_err());
     }
 
-    if name.is_some() {
-        Some((name, version))
-    } else {
-        None
+    #[test]
+    fn test_self_managed_gitlab_host() {
+        let commit = "0123456789abcdef0123456789abcdef01234567";
+        let options = FetchOptions::parse_from(["pkgctx", "--gitlab-host", "git.corp.example"]);
+        assert_eq!(
+            Forge::GitLab.archive_url("group", "pkg", commit, &options),
+            format!(
+                "https://git.corp.example/api/v4/projects/group%2Fpkg/repository/archive.tar.gz?sha={commit}"
+            )
+        );
+        let defaults = FetchOptions::parse_from(["pkgctx"]);
+        assert!(Forge::GitLab
+            .archive_url("group", "pkg", commit, &defaults)
+            .starts_with("https://gitlab.com/api/v4/"));
+
+        assert_eq!(
+            parse_host("GitLab.Corp.example:8443").unwrap(),
+            "gitlab.corp.example:8443"
+        );
+        assert!(parse_host("https://gitlab.corp.example").is_err());
+        assert!(parse_host("user@gitlab.corp.example").is_err());
+        assert!(parse_host("gitlab.corp.example/evil").is_err());
     }
-}
 
-fn parse_setup_py(path: &Path) -> Option<(Option<String>, Option<String>)> {
-    let content = std::fs::read_to_string(path.join("setup.py")).ok()?;
+    #[test]
+    fn test_git_paths_may_contain_at() {
+        let dir = TempDir::new().unwrap();
+        let repo = dir.path().join("pkg@work").join("repo");
+        std::fs::create_dir_all(&repo).unwrap();
+        let canonical = repo.canonicalize().unwrap();
 
-    let mut name = None;
-    let mut version = None;
+        let parse = |spec: String| match PackageSource::parse(&spec, "r").unwrap() {
+            PackageSource::Repository {
+                repository: Repository::Local(path),
+                ref_,
+                ..
+            } => (path, ref_),
+            other => panic!("Expected local repository, got {other:?}"),
+        };
+        let path = repo.display();
+        assert_eq!(parse(format!("git:{path}")), (canonical.clone(), None));
+        assert_eq!(
+            parse(format!("git:{path}@main")),
+            (canonical.clone(), Some("main".to_string()))
+        );
+        assert_eq!(
+            parse(format!("git:{path}@feature/x")),
+            (canonical, Some("feature/x".to_string()))
+        );
+        assert!(PackageSource::parse(&format!("git:{path}@bad..ref"), "r").is_err());
+    }
+
+    #[test]
+    fn test_fetch_local_git_revision() {
+        let repo = TempDir::new().unwrap();
+        let run = |args: &[&str]| {
+            let status = std::process::Command::new("git")
+                .args([
+                    "-c",
+                    "user.name=pkgctx",
+                    "-c",
+                    "user.email=pkgctx@example.com",
+                ])
+                .arg("-C")
+                .arg(repo.path())
+                .args(args)
+                .output()
+                .unwrap()
+                .status;
+            assert!(status.success(), "git {args:?} failed");
+        };
+
+        let pkg = repo.path().join("pkg");
+        std::fs::create_dir(&pkg).unwrap();
+        std::fs::write(pkg.join("DESCRIPTION"), "Package: foo\nVersion: 1.0\n").unwrap();
+        run(&["init", "--quiet"]);
+        run(&["add", "."]);
+        run(&["commit", "--quiet", "-m", "Initial"]);
+        run(&["tag", "v1.0"]);
+
+        // Uncommitted changes must neither be read nor disturbed
+        std::fs::write(pkg.join("DESCRIPTION"), "Package: foo\nVersion: 2.0\n").unwrap();
+
+        let spec = format!("git:{}@v1.0", repo.path().display());
+        let PackageSource::Repository {
+            repository,
+            subdir,
+            ref_,
+        } = PackageSource::parse(&spec, "r").unwrap()
+        else {
+            panic!("Expected repository source");
+        };
+        let fetched = fetch_repo_r_package(
+            &repository,
+            subdir.as_deref(),
+            ref_.as_deref(),
+            &FetchOptions::parse_from(["pkgctx"]),
+        )
+        .unwrap();
+
+        assert_eq!(fetched.name, "foo");
+        assert_eq!(fetched.version.as_deref(), Some("1.0"));
+        assert_eq!(fetched.origin.commit.as_ref().map(String::len), Some(40));
+        assert!(std::fs::read_to_string(pkg.join("DESCRIPTION"))
+            .unwrap()
+            .contains("2.0"));
+    }
+
+    #[test]
+    fn test_git_remote_snapshots_are_cached_by_commit() {
+        let cache_dir = TempDir::new().unwrap();
+        std::env::set_var("PKGCTX_CACHE_DIR", cache_dir.path());
+
+        let repo = TempDir::new().unwrap();
+        let git = |args: &[&str]| {
+            let output = std::process::Command::new("git")
+                .args([
+                    "-c",
+                    "user.name=pkgctx",
+                    "-c",
+                    "user.email=pkgctx@example.com",
+                ])
+                .arg("-C")
+                .arg(repo.path())
+                .args(args)
+                .output()
+                .unwrap();
+            assert!(output.status.success(), "git {args:?} failed");
+            String::from_utf8(output.stdout).unwrap().trim().to_string()
+        };
+        std::fs::write(
+            repo.path().join("DESCRIPTION"),
+            "Package: bar\nVersion: 0.3\n",
+        )
+        .unwrap();
+        git(&["init", "--quiet"]);
+        git(&["add", "."]);
+        git(&["commit", "--quiet", "-m", "Initial"]);
+        let commit = git(&["rev-parse", "HEAD"]);
+
+        let url = url::Url::from_directory_path(repo.path()).unwrap();
+        let repository = Repository::Remote(url.to_string());
+        let fetch = |options: &FetchOptions| {
+            fetch_repo_r_package(&repository, None, Some(&commit), options)
+        };
+        let first = fetch(&FetchOptions::parse_from(["pkgctx"])).unwrap();
+        assert_eq!(first.origin.commit.as_deref(), Some(commit.as_str()));
+
+        // A pinned commit is served from the cache without touching the remote
+        repo.close().unwrap();
+        let cached = fetch(&FetchOptions::parse_from(["pkgctx", "--offline"])).unwrap();
+        assert_eq!(cached.name, "bar");
+        assert_eq!(cached.version.as_deref(), Some("0.3"));
+        assert_eq!(cached.origin.commit.as_deref(), Some(commit.as_str()));
+    }
+
+    #[test]
+    fn test_check_expected_commit() {
+        let commit = "0123456789abcdef0123456789abcdef01234567";
+        let options = |sha: &str| FetchOptions::parse_from(["pkgctx", "--expect-sha", sha]);
+
+        assert!(check_expected_commit(commit, &FetchOptions::parse_from(["pkgctx"])).is_ok());
+        assert!(check_expected_commit(commit, &options(commit)).is_ok());
+        assert!(check_expected_commit(commit, &options("0123456789AB")).is_ok());
+        assert!(check_expected_commit(commit, &options("fedcba9876")).is_err());
+        // Too short to identify a commit
+        assert!(check_expected_commit(commit, &options("0123")).is_err());
+        assert!(is_commit_sha(commit) && !is_commit_sha("main"));
+    }
+
+    #[test]
+    fn test_locate_package_dir() {
+        let root = TempDir::new().unwrap();
+        let pkg = root.path().join("bindings").join("r");
+        std::fs::create_dir_all(&pkg).unwrap();
+        std::fs::write(pkg.join("DESCRIPTION"), "Package: foo\n").unwrap();
+
+        // Discovered below the root, or below an explicit subdirectory
+        assert_eq!(
+            locate_package_dir(root.path(), None, R_PACKAGE_MARKERS).unwrap(),
+            pkg
+        );
+        assert_eq!(
+            locate_package_dir(root.path(), Some("bindings"), R_PACKAGE_MARKERS).unwrap(),
+            pkg
+        );
+        assert!(locate_package_dir(root.path(), Some("missing"), R_PACKAGE_MARKERS).is_err());
+    }
+
+    #[test]
+    fn test_fetch_python_wheel() {
+        let dir = TempDir::new().unwrap();
+        let wheel_path = dir.path().join("mypkg-1.2.0-py3-none-any.whl");
+        let mut wheel = zip::ZipWriter::new(std::fs::File::create(&wheel_path).unwrap());
+        let options = zip::write::SimpleFileOptions::default();
+        wheel.start_file("mypkg/__init__.py", options).unwrap();
+        std::io::Write::write_all(&mut wheel, b"def f():\n    pass\n").unwrap();
+        wheel
+            .start_file("mypkg-1.2.0.dist-info/METADATA", options)
+            .unwrap();
+        std::io::Write::write_all(
+            &mut wheel,
+            b"Metadata-Version: 2.1\nName: mypkg\nVersion: 1.2.0\n\nLong description.\n",
+        )
+        .unwrap();
+        wheel.finish().unwrap();
+
+        let source = PackageSource::parse(wheel_path.to_str().unwrap(), "python").unwrap();
+        assert!(matches!(source, PackageSource::Archive(_)));
+
+        let pkg = fetch_python_archive(&wheel_path).unwrap();
+        assert_eq!(pkg.name, "mypkg");
+        assert_eq!(pkg.version.as_deref(), Some("1.2.0"));
+        assert!(pkg.source_path.join("mypkg").join("__init__.py").exists());
+    }
+
+    /// Write a zip archive (wheel or zip sdist) holding `members`
+    fn write_zip(path: &Path, members: &[(&str, &str)]) {
+        let mut archive = zip::ZipWriter::new(std::fs::File::create(path).unwrap());
+        for (name, content) in members {
+            archive
+                .start_file(*name, zip::write::SimpleFileOptions::default())
+                .unwrap();
+            std::io::Write::write_all(&mut archive, content.as_bytes()).unwrap();
+        }
+        archive.finish().unwrap();
+    }
 
-    for line in content.lines() {
-        let line = line.trim();
-        if (line.contains("name=") || line.contains("name =")) && name.is_none() {
-            name = extract_python_string_value(line, "name");
+    #[test]
+    fn test_pypi_prefers_sdist_then_pure_wheel_then_platform_wheel() {
+        let index = TempDir::new().unwrap();
+        let files = index.path().join("files");
+        std::fs::create_dir(&files).unwrap();
+        let metadata =
+            |version: &str| format!("Metadata-Version: 2.1\nName: mypkg\nVersion: {version}\n");
+        let module = ("mypkg/__init__.py", "def f():\n    pass\n");
+
+        let sdist_metadata = metadata("2.0");
+        write_zip(
+            &files.join("mypkg-2.0.zip"),
+            &[
+                ("mypkg-2.0/PKG-INFO", &sdist_metadata),
+                ("mypkg-2.0/mypkg/__init__.py", module.1),
+            ],
+        );
+        let wheel_metadata = metadata("2.0");
+        for wheel in ["mypkg-2.0-py3-none-any.whl", "mypkg-1.0-py3-none-any.whl"] {
+            write_zip(
+                &files.join(wheel),
+                &[module, ("mypkg-2.0.dist-info/METADATA", &wheel_metadata)],
+            );
         }
-        if (line.contains("version=") || line.contains("version =")) && version.is_none() {
-            version = extract_python_string_value(line, "version");
+        let platform_metadata = metadata("2.0.post1");
+        for wheel in [
+            "mypkg-1.0-cp312-cp312-manylinux_2_17_x86_64.whl",
+            "mypkg-2.0.post1-cp312-cp312-manylinux_2_17_x86_64.whl",
+        ] {
+            write_zip(
+                &files.join(wheel),
+                &[
+                    module,
+                    ("mypkg/_speedups.pyi", "def g() -> None: ...\n"),
+                    ("mypkg/_speedups.cpython-312-x86_64-linux-gnu.so", "\x7fELF"),
+                    ("mypkg-2.0.post1.dist-info/METADATA", &platform_metadata),
+                ],
+            );
         }
+
+        let page = index.path().join("simple").join("mypkg");
+        std::fs::create_dir_all(&page).unwrap();
+        let links: String = std::fs::read_dir(&files)
+            .unwrap()
+            .map(|entry| {
+                let name = entry.unwrap().file_name().into_string().unwrap();
+                format!("<a href=\"../../files/{name}\">{name}</a>\n")
+            })
+            .collect();
+        std::fs::write(page.join("index.html"), links).unwrap();
+
+        let simple = url::Url::from_directory_path(index.path().join("simple")).unwrap();
+        let options =
+            FetchOptions::parse_from(["pkgctx", "--no-cache", "--pypi-index", simple.as_str()]);
+        let fetch = |version: Option<&str>| fetch_pypi_package("mypkg", version, &options).unwrap();
+
+        // The newest final release (a post-release) only has a platform
+        // wheel: its Python sources, stubs and metadata are kept
+        let latest = fetch(None);
+        assert_eq!(latest.version.as_deref(), Some("2.0.post1"));
+        assert_eq!(latest.origin.artifact.as_deref(), Some("platform-wheel"));
+        let root = &latest.source_path;
+        assert!(root.join("mypkg/__init__.py").exists());
+        assert!(root.join("mypkg/_speedups.pyi").exists());
+        assert!(root.join("mypkg-2.0.post1.dist-info/METADATA").exists());
+        assert!(!root
+            .join("mypkg/_speedups.cpython-312-x86_64-linux-gnu.so")
+            .exists());
+
+        let sdist = fetch(Some("2.0"));
+        assert_eq!(sdist.origin.artifact.as_deref(), Some("sdist"));
+        assert!(sdist.source_path.join("PKG-INFO").exists());
+
+        let wheel = fetch(Some("1.0"));
+        assert_eq!(wheel.origin.artifact.as_deref(), Some("wheel"));
+        assert_eq!(
+            wheel
+                .origin
+                .source_url
+                .as_deref()
+                .map(|u| u.ends_with("mypkg-1.0-py3-none-any.whl")),
+            Some(true)
+        );
     }
 
-    Some((name, version))
-}
+    /// Write the source tarball of an R package into `contrib_dir`
+    fn write_r_tarball(contrib_dir: &Path, name: &str, version: &str) {
+        std::fs::create_dir_all(contrib_dir).unwrap();
+        let tarball =
+            std::fs::File::create(contrib_dir.join(format!("{name}_{version}.tar.gz"))).unwrap();
+        let mut builder = tar::Builder::new(flate2::write::GzEncoder::new(
+            tarball,
+            flate2::Compression::default(),
+        ));
+        let description = format!("Package: {name}\nVersion: {version}\nTitle: Test\n");
+        let mut header = tar::Header::new_gnu();
+        header.set_size(description.len() as u64);
+        header.set_mode(0o644);
+        builder
+            .append_data(
+                &mut header,
+                format!("{name}/DESCRIPTION"),
+                description.as_bytes(),
+            )
+            .unwrap();
+        builder.into_inner().unwrap().finish().unwrap();
+    }
 
-fn extract_toml_string_value(line: &str) -> Option<String> {
-    let parts: Vec<&str> = line.splitn(2, '=').collect();
-    if parts.len() == 2 {
-        let val = parts[1].trim().trim_matches('"').trim_matches('\'');
-        if !val.is_empty() {
-            return Some(val.to_string());
+    #[test]
+    fn test_runiverse_records_the_upstream_commit() {
+        let universe = TempDir::new().unwrap();
+        let contrib = universe.path().join("src/contrib");
+        write_r_tarball(&contrib, "tidyup", "0.2.0.9001");
+        std::fs::write(
+            contrib.join("PACKAGES"),
+            "Package: other\nVersion: 1.0\nRemoteSha: 1111111111111111111111111111111111111111\n\n\
+             Package: tidyup\nVersion: 0.2.0.9001\n\
+             RemoteUrl: https://github.com/someone/tidyup\n\
+             RemoteSha: 0123456789abcdef0123456789abcdef01234567\n",
+        )
+        .unwrap();
+
+        let repo = url::Url::from_directory_path(universe.path()).unwrap();
+        let repo = repo.as_str().trim_end_matches('/');
+        let options = FetchOptions::parse_from(["pkgctx", "--no-cache"]);
+        let pkg = fetch_universe_package(repo, "someone", "tidyup", &options).unwrap();
+        assert_eq!(pkg.version.as_deref(), Some("0.2.0.9001"));
+        assert_eq!(
+            pkg.origin.commit.as_deref(),
+            Some("0123456789abcdef0123456789abcdef01234567")
+        );
+        assert!(pkg.source_path.join("DESCRIPTION").exists());
+
+        let options = FetchOptions::parse_from([
+            "pkgctx",
+            "--no-cache",
+            "--expect-sha",
+            "1111111111111111111111111111111111111111",
+        ]);
+        let Err(error) = fetch_universe_package(repo, "someone", "tidyup", &options) else {
+            panic!("A moved universe commit should not match --expect-sha");
+        };
+        assert!(error
+            .to_string()
+            .contains("does not match the expected SHA"));
+    }
+
+    #[test]
+    fn test_bioconductor_release_pin() {
+        match PackageSource::parse("bioc:limma@3.18", "r").unwrap() {
+            PackageSource::Bioconductor { name, release } => {
+                assert_eq!(name, "limma");
+                assert_eq!(release.as_deref(), Some("3.18"));
+            }
+            other => panic!("Expected Bioconductor source, got {other:?}"),
         }
+        assert!(PackageSource::parse("bioc:limma@devel", "r").is_err());
+        assert!(PackageSource::parse("bioc:limma@3.18.1", "r").is_err());
+
+        // A Bioconductor site with two releases; the experiment data
+        // repository only exists for 3.18
+        let site = TempDir::new().unwrap();
+        let packages = |release: &str, repo: &str, name: &str, version: &str| {
+            let contrib = site
+                .path()
+                .join(format!("packages/{release}/{repo}/src/contrib"));
+            write_r_tarball(&contrib, name, version);
+            let index = contrib.join("PACKAGES");
+            let mut content = std::fs::read_to_string(&index).unwrap_or_default();
+            content.push_str(&format!("Package: {name}\nVersion: {version}\n\n"));
+            std::fs::write(index, content).unwrap();
+        };
+        packages("3.18", "bioc", "limma", "3.58.1");
+        packages("3.18", "data/experiment", "airway", "1.22.0");
+        packages("3.19", "bioc", "limma", "3.60.0");
+
+        let bioc_url = url::Url::from_directory_path(site.path()).unwrap();
+        let bioc_url = bioc_url.as_str().trim_end_matches('/');
+        let options = FetchOptions::parse_from(["pkgctx", "--no-cache"]);
+
+        let limma = fetch_bioconductor_from(bioc_url, "limma", Some("3.18"), &options).unwrap();
+        assert_eq!(limma.version.as_deref(), Some("3.58.1"));
+        assert_eq!(
+            limma.origin.source_url.as_deref(),
+            Some(format!("{bioc_url}/packages/3.18/bioc/src/contrib/limma_3.58.1.tar.gz").as_str())
+        );
+
+        let airway = fetch_bioconductor_from(bioc_url, "airway", Some("3.18"), &options).unwrap();
+        assert_eq!(
+            airway.origin.source_url.as_deref(),
+            Some(
+                format!(
+                    "{bioc_url}/packages/3.18/data/experiment/src/contrib/airway_1.22.0.tar.gz"
+                )
+                .as_str()
+            )
+        );
+        assert!(fetch_bioconductor_from(bioc_url, "airway", Some("3.19"), &options).is_err());
+
+        let extract_options = crate::ExtractOptions::parse_from(["pkgctx"]);
+        let records =
+            crate::r_source_extractor::extract_from_source(&limma, &extract_options).unwrap();
+        let Some(crate::schema::Record::Package(record)) = records.first() else {
+            panic!("Expected a package record first, got {records:?}");
+        };
+        assert_eq!(record.bioconductor_release.as_deref(), Some("3.18"));
+    }
+
+    #[test]
+    fn test_compare_versions() {
+        use std::cmp::Ordering;
+
+        assert_eq!(compare_versions("1.0.10", "1.0.9"), Ordering::Greater);
+        assert_eq!(compare_versions("1.1-2", "1.1.2"), Ordering::Equal);
+        assert_eq!(compare_versions("2.0.0rc1", "2.0.0"), Ordering::Less);
+        assert_eq!(compare_versions("2.0", "2.0.1"), Ordering::Less);
+        assert_eq!(compare_versions("0.10.0", "0.9.99"), Ordering::Greater);
+        assert_eq!(compare_versions("1.0.post1", "1.0"), Ordering::Greater);
+        assert_eq!(compare_versions("1.0.post1", "1.0.1"), Ordering::Less);
+        assert_eq!(compare_versions("1.0.post1", "1.0rc1"), Ordering::Greater);
+        assert_eq!(compare_versions("1.0.dev1", "1.0a1"), Ordering::Less);
+        assert_eq!(compare_versions("1.0.dev1", "1.0"), Ordering::Less);
+        assert!(is_final_version("2.0.post1") && !is_final_version("2.1.dev3"));
     }
-    None
-}
 
-fn extract_python_string_value(line: &str, key: &str) -> Option<String> {
-    // Match patterns like: name="foo" or name = "foo" or name='foo'
-    let pattern = format!(r#"{key}\s*=\s*["']([^"']+)["']"#);
-    regex::Regex::new(&pattern)
-        .ok()?
-        .captures(line)?
-        .get(1)
-        .map(|m| m.as_str().to_string())
+    #[test]
+    fn test_distribution_file_info() {
+        let info = |filename: &str, name: &str| distribution_file_info(filename, name);
+
+        assert_eq!(
+            info("python-dateutil-2.8.2.tar.gz", "python_dateutil"),
+            Some(("2.8.2".to_string(), Artifact::Sdist))
+        );
+        assert_eq!(
+            info("Django-5.0.zip", "django"),
+            Some(("5.0".to_string(), Artifact::Sdist))
+        );
+        assert_eq!(
+            info("requests-2.31.0-py3-none-any.whl", "requests"),
+            Some(("2.31.0".to_string(), Artifact::PureWheel))
+        );
+        assert_eq!(
+            info(
+                "numpy-1.26.4-cp312-cp312-manylinux_2_17_x86_64.whl",
+                "numpy"
+            ),
+            Some(("1.26.4".to_string(), Artifact::PlatformWheel))
+        );
+        assert!(info("requests-toolbelt-1.0.0.tar.gz", "requests").is_none());
+        assert!(info("requests_toolbelt-1.0.0-py2.py3-none-any.whl", "requests").is_none());
+    }
 }
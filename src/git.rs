@@ -0,0 +1,159 @@
+//! Git repository access
+//!
+//! Snapshots of a revision are taken with `git archive`, which reads straight
+//! from the object database: the working tree and index of a local clone are
+//! never touched. Remote repositories are first fetched into a scratch bare
+//! repository.
+
+use anyhow::{Context, Result};
+use std::path::Path;
+use std::process::Command;
+
+/// Run git with `args` against the repository at `git_dir`, returning stdout
+fn git(git_dir: &Path, args: &[&str]) -> Result<String> {
+    git_with_config(git_dir, args, &[])
+}
+
+/// Run git with extra configuration `(key, value)` pairs, passed through the
+/// environment so that secrets never show up in the command line
+fn git_with_config(git_dir: &Path, args: &[&str], config: &[(String, String)]) -> Result<String> {
+    let mut command = Command::new("git");
+    if !config.is_empty() {
+        command.env("GIT_CONFIG_COUNT", config.len().to_string());
+        for (i, (key, value)) in config.iter().enumerate() {
+            command.env(format!("GIT_CONFIG_KEY_{i}"), key);
+            command.env(format!("GIT_CONFIG_VALUE_{i}"), value);
+        }
+    }
+    let output = command
+        .arg("--git-dir")
+        .arg(git_dir)
+        .args(args)
+        // Never wait for credentials on a terminal
+        .env("GIT_TERMINAL_PROMPT", "0")
+        .output()
+        .context("Failed to execute git (is it installed?)")?;
+
+    if !output.status.success() {
+        // git echoes remote URLs, which may carry credentials
+        let stderr = crate::auth::redact_text(String::from_utf8_lossy(&output.stderr).trim());
+        anyhow::bail!("git {} failed: {stderr}", args[0]);
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// Locate the git directory of a repository (`.git` of a clone, or the
+/// repository itself when bare)
+pub fn git_dir(repo: &Path) -> Result<std::path::PathBuf> {
+    let dot_git = repo.join(".git");
+    if dot_git.exists() {
+        return Ok(dot_git);
+    }
+    if repo.join("objects").is_dir() && repo.join("HEAD").is_file() {
+        return Ok(repo.to_path_buf());
+    }
+    anyhow::bail!("Not a git repository: {}", repo.display())
+}
+
+/// Resolve a revision (branch, tag or possibly abbreviated SHA) to a full
+/// commit SHA
+pub fn resolve_commit(git_dir: &Path, rev: &str) -> Result<String> {
+    check_revision(rev)?;
+    git(
+        git_dir,
+        &[
+            "rev-parse",
+            "--verify",
+            "--quiet",
+            &format!("{rev}^{{commit}}"),
+        ],
+    )
+    .with_context(|| format!("Unknown revision '{rev}'"))
+}
+
+/// Fetch `rev` (or the default branch) from `url` into a new bare repository
+/// at `git_dir`, returning the fetched commit SHA.
+///
+/// HTTPS remotes on GitHub or the GitLab host are sent the matching token
+/// from [`crate::auth`], unless the URL carries credentials of its own.
+/// A shallow fetch of just the requested revision is tried first; servers
+/// that refuse it (or abbreviated SHAs) fall back to fetching all branches
+/// and tags.
+pub fn fetch(url: &str, rev: Option<&str>, git_dir: &Path) -> Result<String> {
+    if let Some(rev) = rev {
+        check_revision(rev)?;
+    }
+
+    let status = Command::new("git")
+        .args(["init", "--bare", "--quiet"])
+        .arg(git_dir)
+        .status()
+        .context("Failed to execute git (is it installed?)")?;
+    if !status.success() {
+        anyhow::bail!("Failed to create a scratch git repository");
+    }
+
+    let config = fetch_config(url);
+    let wanted = rev.unwrap_or("HEAD");
+    let shallow = git_with_config(
+        git_dir,
+        &["fetch", "--quiet", "--depth", "1", "--", url, wanted],
+        &config,
+    );
+    if shallow.is_ok() {
+        return resolve_commit(git_dir, "FETCH_HEAD");
+    }
+
+    git_with_config(
+        git_dir,
+        &[
+            "fetch",
+            "--quiet",
+            "--",
+            url,
+            "+refs/heads/*:refs/heads/*",
+            "+refs/tags/*:refs/tags/*",
+        ],
+        &config,
+    )
+    .with_context(|| format!("Failed to fetch {}", crate::auth::redact(url)))?;
+    resolve_commit(git_dir, wanted)
+}
+
+/// Configuration sending the forge token for `url`, scoped to its host so
+/// that it is not passed on through redirects
+fn fetch_config(url: &str) -> Vec<(String, String)> {
+    let Ok(parsed) = url::Url::parse(url) else {
+        return Vec::new();
+    };
+    let Some(authorization) = crate::auth::Credentials::get().git_authorization(&parsed) else {
+        return Vec::new();
+    };
+    let origin = parsed.origin().ascii_serialization();
+    vec![(
+        format!("http.{origin}/.extraHeader"),
+        format!("Authorization: {authorization}"),
+    )]
+}
+
+/// Write the tree of `commit` to a tar file at `dest`, with every path under
+/// `prefix/`
+pub fn archive(git_dir: &Path, commit: &str, prefix: &str, dest: &Path) -> Result<()> {
+    check_revision(commit)?;
+    let output = format!("--output={}", dest.display());
+    let prefix = format!("--prefix={prefix}/");
+    git(
+        git_dir,
+        &["archive", "--format=tar", &prefix, &output, commit],
+    )
+    .with_context(|| format!("Failed to archive commit {commit}"))?;
+    Ok(())
+}
+
+/// Reject revisions that git would parse as options
+fn check_revision(rev: &str) -> Result<()> {
+    if rev.is_empty() || rev.starts_with('-') {
+        anyhow::bail!("Invalid git revision: '{rev}'");
+    }
+    Ok(())
+}
@@ -78,12 +78,29 @@ mod tests {
                 name: "test".to_string(),
                 version: "1.0.0".to_string(),
                 language: "R".to_string(),
+                artifact: None,
+                commit: None,
+                bioconductor_release: None,
                 description: None,
+                license: None,
+                url: Vec::new(),
+                bug_reports: None,
+                depends: Vec::new(),
+                imports: Vec::new(),
+                suggests: Vec::new(),
+                system_requirements: None,
+                encoding: None,
+                namespace_imports: BTreeMap::new(),
+                requires_python: None,
+                dependencies: Vec::new(),
+                classifiers: Vec::new(),
                 llm_hints: vec![],
                 common_arguments: BTreeMap::new(),
+                provenance: None,
             }),
             Record::Function(FunctionRecord {
                 name: "func1".to_string(),
+                package: None,
                 exported: true,
                 signature: "func1(data, x)".to_string(),
                 purpose: None,
@@ -103,6 +120,7 @@ mod tests {
             }),
             Record::Function(FunctionRecord {
                 name: "func2".to_string(),
+                package: None,
                 exported: true,
                 signature: "func2(data, y)".to_string(),
                 purpose: None,
@@ -122,6 +140,7 @@ mod tests {
             }),
             Record::Function(FunctionRecord {
                 name: "func3".to_string(),
+                package: None,
                 exported: true,
                 signature: "func3(data, z)".to_string(),
                 purpose: None,
@@ -0,0 +1,358 @@
+//! Minimal HTTP client for package downloads
+//!
+//! Fetches index files and archives in-process. `file://` URLs are read
+//! directly from disk so that local mirrors work without a web server.
+//! Requests carry credentials from [`crate::auth`]; messages only ever show
+//! URLs without their user info.
+
+use crate::auth::{self, Credentials};
+use crate::error::FetchError;
+use anyhow::{Context, Result};
+use std::fs::File;
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use std::time::Duration;
+
+/// Timeout and retry policy for requests
+#[derive(Debug, Clone)]
+pub struct Settings {
+    /// Limit for establishing a connection and for each read
+    pub timeout: Duration,
+    /// Further attempts after a transient failure (timeouts, 5xx, 429)
+    pub retries: u32,
+    /// Delay before the first retry, doubled before each following one
+    pub backoff: Duration,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            timeout: Duration::from_secs(60),
+            retries: 3,
+            backoff: Duration::from_secs(1),
+        }
+    }
+}
+
+static SETTINGS: OnceLock<Settings> = OnceLock::new();
+
+/// Set the request policy; must happen before the first request to apply
+pub fn configure(settings: Settings) {
+    let _ = SETTINGS.set(settings);
+}
+
+fn settings() -> &'static Settings {
+    SETTINGS.get_or_init(Settings::default)
+}
+
+/// Shared agent so connections are reused across requests
+fn agent() -> &'static ureq::Agent {
+    static AGENT: OnceLock<ureq::Agent> = OnceLock::new();
+    AGENT.get_or_init(|| {
+        ureq::AgentBuilder::new()
+            .user_agent(concat!("pkgctx/", env!("CARGO_PKG_VERSION")))
+            .timeout_connect(settings().timeout)
+            .timeout_read(settings().timeout)
+            .build()
+    })
+}
+
+/// A failed attempt, and whether trying again might succeed
+struct Failure {
+    error: anyhow::Error,
+    transient: bool,
+}
+
+impl Failure {
+    fn permanent(error: impl Into<anyhow::Error>) -> Self {
+        Self {
+            error: error.into(),
+            transient: false,
+        }
+    }
+
+    fn transient(error: impl Into<anyhow::Error>) -> Self {
+        Self {
+            error: error.into(),
+            transient: true,
+        }
+    }
+}
+
+/// Run `attempt` until it succeeds, fails permanently or runs out of retries
+fn with_retries<T>(
+    settings: &Settings,
+    mut attempt: impl FnMut() -> std::result::Result<T, Failure>,
+) -> Result<T> {
+    let mut delay = settings.backoff;
+    let mut retries_left = settings.retries;
+    loop {
+        match attempt() {
+            Ok(value) => return Ok(value),
+            Err(failure) if failure.transient && retries_left > 0 => {
+                eprintln!(
+                    "  → {}; retrying in {:.1}s",
+                    failure.error,
+                    delay.as_secs_f64()
+                );
+                std::thread::sleep(delay);
+                delay *= 2;
+                retries_left -= 1;
+            }
+            Err(failure) => return Err(failure.error),
+        }
+    }
+}
+
+/// Open a reader over the body at `url`, authenticating with `credentials`
+fn open_with(
+    url: &str,
+    credentials: &Credentials,
+) -> std::result::Result<Box<dyn Read + Send + Sync>, Failure> {
+    if let Some(path) = local_path(url) {
+        return match File::open(&path) {
+            Ok(file) => Ok(Box::new(file)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Err(Failure::permanent(
+                FetchError::NotFound(format!("No such file: {}", path.display())),
+            )),
+            Err(e) => Err(Failure::permanent(
+                anyhow::Error::new(e).context(format!("Failed to open {}", path.display())),
+            )),
+        };
+    }
+
+    // User info moves into the Authorization header, so that neither the
+    // request line nor any error message contains it
+    let mut parsed = url::Url::parse(url)
+        .with_context(|| format!("Invalid URL: {}", auth::redact(url)))
+        .map_err(Failure::permanent)?;
+    let authorization = credentials.authorization(&parsed);
+    let _ = parsed.set_username("");
+    let _ = parsed.set_password(None);
+    let url = parsed.as_str();
+
+    let mut request = agent().get(url);
+    if let Some(value) = &authorization {
+        request = request.set("Authorization", value);
+    }
+    match request.call() {
+        Ok(response) => Ok(Box::new(response.into_reader())),
+        Err(ureq::Error::Status(code @ (404 | 410), _)) => Err(Failure::permanent(
+            FetchError::NotFound(format!("HTTP {code} for {url}")),
+        )),
+        Err(ureq::Error::Status(code @ (401 | 403), _)) => {
+            let hint = if authorization.is_some() {
+                "credentials were rejected"
+            } else {
+                "set GITHUB_TOKEN/GITLAB_TOKEN or add the host to ~/.netrc"
+            };
+            Err(Failure::permanent(FetchError::Network(format!(
+                "HTTP {code} for {url} ({hint})"
+            ))))
+        }
+        Err(ureq::Error::Status(code, _)) => {
+            let error = FetchError::Network(format!("HTTP {code} for {url}"));
+            if code == 408 || code == 429 || code >= 500 {
+                Err(Failure::transient(error))
+            } else {
+                Err(Failure::permanent(error))
+            }
+        }
+        Err(ureq::Error::Transport(transport)) => {
+            let mut detail = transport.kind().to_string();
+            if let Some(message) = transport.message() {
+                detail.push_str(&format!(": {message}"));
+            }
+            if let Some(source) = std::error::Error::source(&transport) {
+                detail.push_str(&format!(" ({source})"));
+            }
+            Err(Failure::transient(FetchError::Network(format!(
+                "Failed to request {url}: {detail}"
+            ))))
+        }
+    }
+}
+
+/// Read failures midway through a body are worth another attempt
+fn read_failure(url: &str, error: &io::Error) -> Failure {
+    Failure::transient(FetchError::Network(format!(
+        "Failed to read response from {}: {error}",
+        auth::redact(url)
+    )))
+}
+
+/// Fetch the body at `url` into memory
+pub fn get(url: &str) -> Result<Vec<u8>> {
+    with_retries(settings(), || {
+        let mut body = Vec::new();
+        open_with(url, Credentials::get())?
+            .read_to_end(&mut body)
+            .map_err(|e| read_failure(url, &e))?;
+        Ok(body)
+    })
+}
+
+/// Fetch the body at `url` as UTF-8 text
+pub fn get_string(url: &str) -> Result<String> {
+    String::from_utf8(get(url)?)
+        .with_context(|| format!("Response from {} is not UTF-8", auth::redact(url)))
+}
+
+/// Fetch and parse the JSON document at `url`
+pub fn get_json(url: &str) -> Result<serde_json::Value> {
+    serde_json::from_slice(&get(url)?)
+        .with_context(|| format!("Invalid JSON from {}", auth::redact(url)))
+}
+
+/// Download the body at `url` to the file `dest`
+pub fn download(url: &str, dest: &Path) -> Result<()> {
+    with_retries(settings(), || {
+        let mut reader = open_with(url, Credentials::get())?;
+        let mut file = File::create(dest)
+            .with_context(|| format!("Failed to create {}", dest.display()))
+            .map_err(Failure::permanent)?;
+        let mut buffer = vec![0u8; 64 * 1024];
+        loop {
+            let read = match reader.read(&mut buffer) {
+                Ok(0) => return Ok(()),
+                Ok(n) => n,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(read_failure(url, &e)),
+            };
+            file.write_all(&buffer[..read])
+                .with_context(|| format!("Failed to write {}", dest.display()))
+                .map_err(Failure::permanent)?;
+        }
+    })
+}
+
+/// Resolve a `file://` URL to a filesystem path.
+///
+/// Directories resolve to their `index.html`, as a static web server would,
+/// so that a simple index can be mirrored as plain files.
+fn local_path(url: &str) -> Option<PathBuf> {
+    if !url.starts_with("file://") {
+        return None;
+    }
+    let path = url::Url::parse(url).ok()?.to_file_path().ok()?;
+    if path.is_dir() {
+        Some(path.join("index.html"))
+    } else {
+        Some(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader};
+    use std::net::TcpListener;
+    use std::thread;
+
+    /// Answer one request per entry of `statuses`, returning the
+    /// `Authorization` header of each
+    fn serve(statuses: &[&'static str]) -> (String, thread::JoinHandle<Vec<Option<String>>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let statuses = statuses.to_vec();
+        let handle = thread::spawn(move || {
+            let mut headers = Vec::new();
+            for status in statuses {
+                let (mut stream, _) = listener.accept().unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut authorization = None;
+                loop {
+                    let mut line = String::new();
+                    reader.read_line(&mut line).unwrap();
+                    if line.trim().is_empty() {
+                        break;
+                    }
+                    if let Some((name, value)) = line.split_once(':') {
+                        if name.eq_ignore_ascii_case("authorization") {
+                            authorization = Some(value.trim().to_string());
+                        }
+                    }
+                }
+                write!(
+                    stream,
+                    "HTTP/1.1 {status}\r\nContent-Length: 2\r\nConnection: close\r\n\r\nok"
+                )
+                .unwrap();
+                headers.push(authorization);
+            }
+            headers
+        });
+        (format!("127.0.0.1:{port}"), handle)
+    }
+
+    fn quick_retries(retries: u32) -> Settings {
+        Settings {
+            timeout: Duration::from_secs(5),
+            retries,
+            backoff: Duration::from_millis(10),
+        }
+    }
+
+    #[test]
+    fn test_url_credentials_are_sent_and_never_shown() {
+        let (host, server) = serve(&["200 OK", "401 Unauthorized"]);
+
+        let url = format!("http://ci:s3cret@{host}/simple/pkg/");
+        let mut body = String::new();
+        let _ = open_with(&url, &Credentials::default())
+            .ok()
+            .unwrap()
+            .read_to_string(&mut body);
+        assert_eq!(body, "ok");
+
+        let url = format!("http://ci:s3cret@{host}/private.tar.gz");
+        let failure = open_with(&url, &Credentials::default()).err().unwrap();
+        let error = format!("{:#}", failure.error);
+        assert!(error.contains("HTTP 401"));
+        assert!(!error.contains("s3cret"), "{error}");
+        assert!(!failure.transient);
+
+        let headers = server.join().unwrap();
+        assert_eq!(headers[0].as_deref(), Some("Basic Y2k6czNjcmV0"));
+    }
+
+    #[test]
+    fn test_status_codes_are_classified() {
+        let (host, server) = serve(&["404 Not Found", "503 Service Unavailable", "200 OK"]);
+        let url = format!("http://{host}/pkg.tar.gz");
+
+        // A missing file fails at once, without credentials or retries
+        let error = with_retries(&quick_retries(3), || {
+            open_with(&url, &Credentials::default())
+        })
+        .err()
+        .unwrap();
+        assert!(crate::error::is_not_found(&error));
+
+        // A server error is retried
+        let mut body = String::new();
+        let _ = with_retries(&quick_retries(3), || {
+            open_with(&url, &Credentials::default())
+        })
+        .unwrap()
+        .read_to_string(&mut body);
+        assert_eq!(body, "ok");
+
+        assert_eq!(server.join().unwrap(), vec![None, None, None]);
+    }
+
+    #[test]
+    fn test_retries_give_up_with_a_network_error() {
+        let (host, server) = serve(&["502 Bad Gateway", "502 Bad Gateway"]);
+        let url = format!("http://{host}/PACKAGES");
+        let error = with_retries(&quick_retries(1), || {
+            open_with(&url, &Credentials::default())
+        })
+        .err()
+        .unwrap();
+        server.join().unwrap();
+        assert_eq!(crate::error::exit_code(&error), 5);
+        assert!(error.to_string().contains("HTTP 502"));
+    }
+}
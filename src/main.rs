@@ -3,17 +3,36 @@
 //! Extracts structured, compact API specifications from R or Python packages
 //! for use in LLMs, minimizing tokens while maximizing context.
 
+mod archive;
+mod auth;
+mod cache;
+mod checksum;
 mod compact;
+mod dcf;
+mod deps;
+mod error;
 mod fetch;
+mod git;
 mod hoist;
+mod http;
+mod namespace;
+mod project;
+mod python_metadata;
 mod python_source_extractor;
+mod r_lexer;
+mod r_parser;
 mod r_source_extractor;
+mod s3;
 mod schema;
 
-use anyhow::Result;
+use anyhow::{Context, Result};
 use clap::{Parser, Subcommand, ValueEnum};
 use fetch::PackageInfo;
+use std::collections::HashSet;
 use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::time::Duration;
 
 /// Compile software packages into LLM-ready context
 #[derive(Parser)]
@@ -28,21 +47,83 @@ struct Cli {
 enum Commands {
     /// Extract context from an R package (CRAN, GitHub, or local path)
     R {
-        /// Package specifier: name (CRAN), `github:owner/repo[@ref]`, or local path (., ./path, /path)
+        /// Package specifier: name[@version] (CRAN), `bioc:pkg[@release]`, `runiverse:owner/pkg`, `github:owner/repo[/subdir][@ref]`, `git+https://…[@rev]`, `git:/path[@rev]`, local path (., ./path, /path), or source archive
         package: String,
 
         #[command(flatten)]
         options: ExtractOptions,
+
+        #[command(flatten)]
+        fetch_options: FetchOptions,
     },
 
     /// Extract context from a Python package (`PyPI`, GitHub, or local path)
     Python {
-        /// Package specifier: name (`PyPI`), `github:owner/repo[@ref]`, or local path (., ./path, /path)
+        /// Package specifier: name[==version] (`PyPI`), `github:owner/repo[/subdir][@ref]`, `git+https://…[@rev]`, `git:/path[@rev]`, local path (., ./path, /path), or source archive
         package: String,
 
         #[command(flatten)]
         options: ExtractOptions,
+
+        #[command(flatten)]
+        fetch_options: FetchOptions,
+    },
+
+    /// Extract context for every dependency of an R project (renv.lock or DESCRIPTION)
+    RProject {
+        /// Project directory, renv.lock or DESCRIPTION file
+        #[arg(default_value = ".")]
+        project: PathBuf,
+
+        /// Directory to write one context file per package and an index to
+        #[arg(short, long, default_value = "pkgctx")]
+        output_dir: PathBuf,
+
+        #[command(flatten)]
+        options: ExtractOptions,
+
+        #[command(flatten)]
+        fetch_options: FetchOptions,
+    },
+
+    /// Extract context for every dependency of a Python project (uv.lock, poetry.lock, requirements.txt or pyproject.toml)
+    PythonProject {
+        /// Project directory or dependency file
+        #[arg(default_value = ".")]
+        project: PathBuf,
+
+        /// Directory to write one context file per package and an index to
+        #[arg(short, long, default_value = "pkgctx")]
+        output_dir: PathBuf,
+
+        #[command(flatten)]
+        options: ExtractOptions,
+
+        #[command(flatten)]
+        fetch_options: FetchOptions,
     },
+
+    /// Manage the on-disk cache of downloaded package sources
+    Cache {
+        #[command(subcommand)]
+        action: CacheAction,
+    },
+}
+
+#[derive(Subcommand)]
+enum CacheAction {
+    /// List cached package sources
+    List,
+
+    /// Remove cached sources that have not been used recently
+    Prune {
+        /// Remove entries unused for more than this many days
+        #[arg(long, default_value_t = 30)]
+        older_than: u64,
+    },
+
+    /// Remove all cached sources
+    Clear,
 }
 
 #[derive(Parser, Clone)]
@@ -74,6 +155,85 @@ pub struct ExtractOptions {
     /// Omit the LLM instructions header from output
     #[arg(long)]
     pub no_header: bool,
+
+    /// Append the exported API of dependencies, limited to the names the package refers to, following dependencies of dependencies up to DEPTH levels
+    #[arg(long, value_name = "DEPTH", num_args = 0..=1, default_missing_value = "1")]
+    pub with_deps: Option<usize>,
+}
+
+/// CRAN mirror used unless `--cran-repo` says otherwise
+const DEFAULT_CRAN_REPO: &str = "https://cloud.r-project.org";
+
+#[derive(Parser, Clone)]
+pub struct FetchOptions {
+    /// Always download sources, bypassing the on-disk cache
+    #[arg(long)]
+    pub no_cache: bool,
+
+    /// Never access the network; resolve packages from the cache or local (file://) mirrors only
+    #[arg(long)]
+    pub offline: bool,
+
+    /// CRAN-like repository to fetch R packages from (http(s):// or file://)
+    #[arg(long, default_value = DEFAULT_CRAN_REPO)]
+    pub cran_repo: String,
+
+    /// Bioconductor repository (defaults to the repositories of the current release)
+    #[arg(long)]
+    pub bioc_repo: Option<String>,
+
+    /// Python package index (simple API) to fetch from (defaults to pip's `index-url`, then `PyPI`)
+    #[arg(long)]
+    pub pypi_index: Option<String>,
+
+    /// Host of the GitLab instance serving `gitlab:` specs (e.g. a self-managed GitLab)
+    #[arg(long, value_name = "HOST", default_value = "gitlab.com", value_parser = fetch::parse_host)]
+    pub gitlab_host: String,
+
+    /// Fail unless the package's git source resolves to this commit (full or abbreviated SHA); dependencies are not checked
+    #[arg(long, value_name = "SHA")]
+    pub expect_sha: Option<String>,
+
+    /// Seconds to wait for a connection or for data before a request fails
+    #[arg(long, value_name = "SECONDS", default_value_t = 60)]
+    pub timeout: u64,
+
+    /// Times to retry a request after a timeout or server error, with exponential backoff
+    #[arg(long, default_value_t = 3)]
+    pub retries: u32,
+}
+
+impl FetchOptions {
+    /// Request policy for the HTTP client
+    fn http_settings(&self) -> http::Settings {
+        http::Settings {
+            timeout: Duration::from_secs(self.timeout),
+            retries: self.retries,
+            ..http::Settings::default()
+        }
+    }
+
+    /// Hosts that may receive credentials
+    fn auth_settings(&self) -> auth::Settings {
+        let cran_repo = (self.cran_repo != DEFAULT_CRAN_REPO).then(|| self.cran_repo.clone());
+        auth::Settings {
+            gitlab_host: self.gitlab_host.clone(),
+            repositories: cran_repo
+                .into_iter()
+                .chain(self.bioc_repo.clone())
+                .chain(self.pypi_index.clone())
+                .collect(),
+        }
+    }
+
+    /// Options for fetching dependencies: `--expect-sha` only pins the
+    /// package that was asked for
+    fn for_dependencies(&self) -> Self {
+        Self {
+            expect_sha: None,
+            ..self.clone()
+        }
+    }
 }
 
 #[derive(Clone, Copy, ValueEnum)]
@@ -82,89 +242,377 @@ enum OutputFormat {
     Json,
 }
 
-fn main() -> Result<()> {
-    let cli = Cli::parse();
+impl OutputFormat {
+    /// File extension for output written in this format
+    fn extension(self) -> &'static str {
+        match self {
+            Self::Yaml => "yaml",
+            Self::Json => "json",
+        }
+    }
+}
 
+/// Exit status is 0 on success and 1 for most failures; fetch failures use
+/// the codes of [`error::FetchError`] (3 not found, 4 version not found,
+/// 5 network, 6 corrupt archive)
+fn main() -> ExitCode {
+    match run(Cli::parse()) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("Error: {e:?}");
+            ExitCode::from(error::exit_code(&e))
+        }
+    }
+}
+
+fn run(cli: Cli) -> Result<()> {
     match cli.command {
-        Commands::R { package, options } => process_r_package(&package, &options),
-        Commands::Python { package, options } => process_python_package(&package, &options),
+        Commands::R {
+            package,
+            options,
+            fetch_options,
+        } => process_r_package(&package, &options, &fetch_options),
+        Commands::Python {
+            package,
+            options,
+            fetch_options,
+        } => process_python_package(&package, &options, &fetch_options),
+        Commands::RProject {
+            project,
+            output_dir,
+            options,
+            fetch_options,
+        } => process_project(&project, "R", &output_dir, &options, &fetch_options),
+        Commands::PythonProject {
+            project,
+            output_dir,
+            options,
+            fetch_options,
+        } => process_project(&project, "Python", &output_dir, &options, &fetch_options),
+        Commands::Cache { action } => manage_cache(&action),
+    }
+}
+
+/// Run a cache management subcommand
+fn manage_cache(action: &CacheAction) -> Result<()> {
+    let cache = cache::Cache::open()?;
+
+    match action {
+        CacheAction::List => {
+            let entries = cache.entries()?;
+            if entries.is_empty() {
+                eprintln!("Cache is empty ({})", cache.root().display());
+            }
+            for entry in entries {
+                println!(
+                    "{}\t{}\t{}\t{}\t{} KiB",
+                    entry.kind,
+                    entry.name,
+                    entry.version,
+                    entry.repository,
+                    entry.size() / 1024
+                );
+            }
+        }
+        CacheAction::Prune { older_than } => {
+            let max_age = std::time::Duration::from_secs(older_than * 24 * 60 * 60);
+            let removed = cache.prune(max_age)?;
+            for entry in &removed {
+                eprintln!("Removed {} {} {}", entry.kind, entry.name, entry.version);
+            }
+            eprintln!("Pruned {} cache entries", removed.len());
+        }
+        CacheAction::Clear => {
+            cache.clear()?;
+            eprintln!("Cleared cache at {}", cache.root().display());
+        }
     }
+
+    Ok(())
 }
 
 /// Process an R package from any source
-fn process_r_package(package: &str, options: &ExtractOptions) -> Result<()> {
+fn process_r_package(
+    package: &str,
+    options: &ExtractOptions,
+    fetch_options: &FetchOptions,
+) -> Result<()> {
     eprintln!("Fetching R package: {package}");
+    http::configure(fetch_options.http_settings());
+    auth::configure(fetch_options.auth_settings());
 
     let source = fetch::PackageSource::parse(package, "r")?;
+    let pkg = fetch_r_package(source, fetch_options)?;
+    process_package(pkg.as_ref(), options, fetch_options, "R")
+}
 
-    match source {
-        fetch::PackageSource::Cran(name) => {
+/// Fetch an R package from any source
+fn fetch_r_package(
+    source: fetch::PackageSource,
+    fetch_options: &FetchOptions,
+) -> Result<Box<dyn PackageInfo>> {
+    Ok(match source {
+        fetch::PackageSource::Cran { name, version } => {
             eprintln!("  → Downloading from CRAN...");
-            let pkg = fetch::fetch_cran_package(&name)?;
-            process_package(&pkg, options, "R")
+            Box::new(fetch::fetch_cran_package(
+                &name,
+                version.as_deref(),
+                fetch_options,
+            )?)
         }
-        fetch::PackageSource::Bioconductor(name) => {
-            eprintln!("  → Downloading from Bioconductor...");
-            let pkg = fetch::fetch_bioconductor_package(&name)?;
-            process_package(&pkg, options, "R")
+        fetch::PackageSource::Bioconductor { name, release } => {
+            match &release {
+                Some(release) => eprintln!("  → Downloading from Bioconductor {release}..."),
+                None => eprintln!("  → Downloading from Bioconductor..."),
+            }
+            Box::new(fetch::fetch_bioconductor_package(
+                &name,
+                release.as_deref(),
+                fetch_options,
+            )?)
         }
-        fetch::PackageSource::GitHub { owner, repo, ref_ } => {
-            eprintln!("  → Downloading from GitHub: {owner}/{repo}...");
-            let pkg = fetch::fetch_github_r_package(&owner, &repo, ref_.as_deref())?;
-            process_package(&pkg, options, "R")
+        fetch::PackageSource::RUniverse { owner, name } => {
+            eprintln!("  → Downloading from r-universe: {owner}...");
+            Box::new(fetch::fetch_runiverse_package(
+                &owner,
+                &name,
+                fetch_options,
+            )?)
+        }
+        fetch::PackageSource::Repository {
+            repository,
+            subdir,
+            ref_,
+        } => {
+            eprintln!("  → Fetching from {repository}...");
+            Box::new(fetch::fetch_repo_r_package(
+                &repository,
+                subdir.as_deref(),
+                ref_.as_deref(),
+                fetch_options,
+            )?)
         }
         fetch::PackageSource::Local(path) => {
             eprintln!("  → Using local path: {}...", path.display());
-            let pkg = fetch::fetch_local_r_package(&path)?;
-            process_package(&pkg, options, "R")
+            Box::new(fetch::fetch_local_r_package(&path)?)
+        }
+        fetch::PackageSource::Archive(path) => {
+            eprintln!("  → Unpacking archive: {}...", path.display());
+            Box::new(fetch::fetch_r_archive(&path)?)
         }
-        fetch::PackageSource::PyPI(_) => {
+        fetch::PackageSource::PyPI { .. } => {
             anyhow::bail!("PyPI source is not valid for R packages")
         }
-    }
+    })
 }
 
 /// Process a Python package from any source
-fn process_python_package(package: &str, options: &ExtractOptions) -> Result<()> {
+fn process_python_package(
+    package: &str,
+    options: &ExtractOptions,
+    fetch_options: &FetchOptions,
+) -> Result<()> {
     eprintln!("Fetching Python package: {package}");
+    http::configure(fetch_options.http_settings());
+    auth::configure(fetch_options.auth_settings());
 
     let source = fetch::PackageSource::parse(package, "python")?;
+    let pkg = fetch_python_package(source, fetch_options)?;
+    process_package(pkg.as_ref(), options, fetch_options, "Python")
+}
 
-    match source {
-        fetch::PackageSource::PyPI(name) => {
+/// Fetch a Python package from any source
+fn fetch_python_package(
+    source: fetch::PackageSource,
+    fetch_options: &FetchOptions,
+) -> Result<Box<dyn PackageInfo>> {
+    Ok(match source {
+        fetch::PackageSource::PyPI { name, version } => {
             eprintln!("  → Downloading from PyPI...");
-            let pkg = fetch::fetch_pypi_package(&name)?;
-            process_package(&pkg, options, "Python")
+            Box::new(fetch::fetch_pypi_package(
+                &name,
+                version.as_deref(),
+                fetch_options,
+            )?)
         }
-        fetch::PackageSource::GitHub { owner, repo, ref_ } => {
-            eprintln!("  → Downloading from GitHub: {owner}/{repo}...");
-            let pkg = fetch::fetch_github_python_package(&owner, &repo, ref_.as_deref())?;
-            process_package(&pkg, options, "Python")
+        fetch::PackageSource::Repository {
+            repository,
+            subdir,
+            ref_,
+        } => {
+            eprintln!("  → Fetching from {repository}...");
+            Box::new(fetch::fetch_repo_python_package(
+                &repository,
+                subdir.as_deref(),
+                ref_.as_deref(),
+                fetch_options,
+            )?)
         }
         fetch::PackageSource::Local(path) => {
             eprintln!("  → Using local path: {}...", path.display());
-            let pkg = fetch::fetch_local_python_package(&path)?;
-            process_package(&pkg, options, "Python")
+            Box::new(fetch::fetch_local_python_package(&path)?)
         }
-        fetch::PackageSource::Cran(_) | fetch::PackageSource::Bioconductor(_) => {
-            anyhow::bail!("CRAN/Bioconductor source is not valid for Python packages")
+        fetch::PackageSource::Archive(path) => {
+            eprintln!("  → Unpacking archive: {}...", path.display());
+            Box::new(fetch::fetch_python_archive(&path)?)
         }
-    }
+        fetch::PackageSource::Cran { .. }
+        | fetch::PackageSource::Bioconductor { .. }
+        | fetch::PackageSource::RUniverse { .. } => {
+            anyhow::bail!("CRAN/Bioconductor/r-universe source is not valid for Python packages")
+        }
+    })
 }
 
-/// Common processing logic for any package type
-fn process_package(pkg: &dyn PackageInfo, options: &ExtractOptions, language: &str) -> Result<()> {
-    eprintln!("  → Version: {}", pkg.version().unwrap_or("unknown"));
-    eprintln!("  → Parsing source...");
+/// Process every dependency of a project, writing one context file per
+/// package and an index of them to `output_dir`.
+///
+/// A dependency that cannot be fetched or extracted is reported and listed
+/// in the index without stopping the others; the run fails at the end.
+fn process_project(
+    project: &Path,
+    language: &str,
+    output_dir: &Path,
+    options: &ExtractOptions,
+    fetch_options: &FetchOptions,
+) -> Result<()> {
+    if fetch_options.expect_sha.is_some() {
+        anyhow::bail!("--expect-sha pins a single package and cannot be used with a project");
+    }
+    http::configure(fetch_options.http_settings());
+    auth::configure(fetch_options.auth_settings());
+
+    let (file, dependencies) = match language {
+        "R" => {
+            let file = project::find_r_project_file(project)?;
+            eprintln!("Reading R project dependencies: {}", file.display());
+            let dependencies = project::read_r_dependencies(&file)?;
+            (file, dependencies)
+        }
+        _ => {
+            let file = project::find_python_project_file(project)?;
+            eprintln!("Reading Python project dependencies: {}", file.display());
+            let dependencies = project::read_python_dependencies(&file)?;
+            (file, dependencies)
+        }
+    };
+    eprintln!("  → Dependencies: {}", dependencies.len());
+
+    std::fs::create_dir_all(output_dir)
+        .with_context(|| format!("Failed to create {}", output_dir.display()))?;
+
+    let mut packages = Vec::new();
+    let mut failed = Vec::new();
+    for dependency in dependencies {
+        eprintln!("Fetching {language} package: {}", dependency.spec);
+        match process_dependency(&dependency, language, output_dir, options, fetch_options) {
+            Ok(entry) => packages.push(entry),
+            Err(e) => {
+                eprintln!("  → Failed: {e:#}");
+                failed.push(project::FailedEntry {
+                    name: dependency.name,
+                    source: dependency.spec,
+                    error: format!("{e:#}"),
+                });
+            }
+        }
+    }
 
-    let records = match language {
-        "R" => r_source_extractor::extract_from_source(pkg, options)?,
-        "Python" => python_source_extractor::extract_from_source(pkg, options)?,
-        _ => anyhow::bail!("Unknown language: {language}"),
+    let index = project::ProjectIndex {
+        project: file.display().to_string(),
+        packages,
+        failed,
     };
+    let index_path = output_dir.join(format!("index.{}", options.format.extension()));
+    let content = match options.format {
+        OutputFormat::Yaml => serde_yaml::to_string(&index)?,
+        OutputFormat::Json => serde_json::to_string_pretty(&index)? + "\n",
+    };
+    std::fs::write(&index_path, content)
+        .with_context(|| format!("Failed to write {}", index_path.display()))?;
+    eprintln!(
+        "Wrote {} context files, indexed in {}",
+        index.packages.len(),
+        index_path.display()
+    );
+
+    if !index.failed.is_empty() {
+        let names: Vec<&str> = index.failed.iter().map(|f| f.name.as_str()).collect();
+        anyhow::bail!(
+            "{} of {} packages could not be processed: {}",
+            names.len(),
+            names.len() + index.packages.len(),
+            names.join(", ")
+        );
+    }
+    Ok(())
+}
+
+/// Fetch and extract one project dependency into its context file
+fn process_dependency(
+    dependency: &project::Dependency,
+    language: &str,
+    output_dir: &Path,
+    options: &ExtractOptions,
+    fetch_options: &FetchOptions,
+) -> Result<project::IndexEntry> {
+    let pkg = fetch_package(&dependency.spec, language, fetch_options)?;
+
+    // Some sources (Bioconductor, r-universe) only serve their current version
+    if let (Some(pinned), Some(fetched)) = (&dependency.version, pkg.version()) {
+        if pinned != fetched {
+            eprintln!(
+                "  → Warning: the project pins {} {pinned}, but {fetched} was fetched",
+                dependency.name
+            );
+        }
+    }
+
+    let records = package_records(pkg.as_ref(), options, fetch_options, language)?;
+    let file_name = format!("{}.ctx.{}", pkg.name(), options.format.extension());
+    let path = output_dir.join(&file_name);
+    let mut out = std::fs::File::create(&path)
+        .with_context(|| format!("Failed to create {}", path.display()))?;
+    output_records(
+        &mut out,
+        &records,
+        options.format,
+        pkg.name(),
+        language,
+        options.no_header,
+    )?;
+
+    Ok(project::IndexEntry {
+        name: pkg.name().to_string(),
+        version: pkg.version().map(str::to_string),
+        source: dependency.spec.clone(),
+        commit: pkg.origin().and_then(|o| o.commit.clone()),
+        file: file_name,
+    })
+}
+
+/// Fetch a package of either language from its specifier
+fn fetch_package(
+    spec: &str,
+    language: &str,
+    fetch_options: &FetchOptions,
+) -> Result<Box<dyn PackageInfo>> {
+    match language {
+        "R" => fetch_r_package(fetch::PackageSource::parse(spec, "r")?, fetch_options),
+        _ => fetch_python_package(fetch::PackageSource::parse(spec, "python")?, fetch_options),
+    }
+}
 
-    let records = apply_transformations(records, options);
+/// Common processing logic for any package type
+fn process_package(
+    pkg: &dyn PackageInfo,
+    options: &ExtractOptions,
+    fetch_options: &FetchOptions,
+    language: &str,
+) -> Result<()> {
+    let records = package_records(pkg, options, fetch_options, language)?;
     output_records(
+        &mut io::stdout().lock(),
         &records,
         options.format,
         pkg.name(),
@@ -173,6 +621,114 @@ fn process_package(pkg: &dyn PackageInfo, options: &ExtractOptions, language: &s
     )
 }
 
+/// Records of a fetched package, followed by those of its dependencies
+/// with `--with-deps`
+fn package_records(
+    pkg: &dyn PackageInfo,
+    options: &ExtractOptions,
+    fetch_options: &FetchOptions,
+    language: &str,
+) -> Result<Vec<schema::Record>> {
+    let mut records = extract_records(pkg, options, language)?;
+    if let Some(depth) = options.with_deps {
+        records = deps::tag_package(records, pkg.name());
+        records.extend(dependency_records(
+            pkg,
+            depth,
+            options,
+            fetch_options,
+            language,
+        ));
+    }
+    Ok(records)
+}
+
+/// Records of the dependencies of `pkg`, up to `depth` levels deep, each
+/// reduced to the names referenced by the packages of the level above.
+///
+/// A dependency that cannot be fetched or extracted is skipped with a
+/// warning, as the context of the package itself is still useful.
+fn dependency_records(
+    pkg: &dyn PackageInfo,
+    depth: usize,
+    options: &ExtractOptions,
+    fetch_options: &FetchOptions,
+    language: &str,
+) -> Vec<schema::Record> {
+    let options = ExtractOptions {
+        include_internal: false,
+        ..options.clone()
+    };
+    let fetch_options = &fetch_options.for_dependencies();
+    let mut seen = HashSet::from([deps::package_key(pkg.name())]);
+    let mut dependencies = deps::direct_dependencies(pkg.source_path(), language);
+    let mut referenced = deps::referenced_names(pkg.source_path(), language);
+    let mut records = Vec::new();
+
+    for level in 1..=depth {
+        let mut next_dependencies = Vec::new();
+        let mut next_referenced = HashSet::new();
+        for dependency in dependencies {
+            if !seen.insert(deps::package_key(&dependency.name)) {
+                continue;
+            }
+            eprintln!("Fetching dependency: {}", dependency.spec);
+            let extracted =
+                fetch_package(&dependency.spec, language, fetch_options).and_then(|dep| {
+                    let dep_records = parse_records(dep.as_ref(), &options, language)?;
+                    if level < depth {
+                        next_dependencies
+                            .extend(deps::direct_dependencies(dep.source_path(), language));
+                        next_referenced.extend(deps::referenced_names(dep.source_path(), language));
+                    }
+                    Ok(deps::reduce_to_referenced(
+                        dep_records,
+                        &referenced,
+                        dep.name(),
+                    ))
+                });
+            match extracted {
+                Ok(dep_records) => records.extend(apply_transformations(dep_records, &options)),
+                Err(e) => eprintln!("  → Skipping {}: {e:#}", dependency.name),
+            }
+        }
+        dependencies = next_dependencies;
+        referenced = next_referenced;
+    }
+    records
+}
+
+/// Extract the records of a fetched package and apply the requested
+/// transformations
+fn extract_records(
+    pkg: &dyn PackageInfo,
+    options: &ExtractOptions,
+    language: &str,
+) -> Result<Vec<schema::Record>> {
+    let records = parse_records(pkg, options, language)?;
+    Ok(apply_transformations(records, options))
+}
+
+/// Extract the records of a fetched package as found in its source
+fn parse_records(
+    pkg: &dyn PackageInfo,
+    options: &ExtractOptions,
+    language: &str,
+) -> Result<Vec<schema::Record>> {
+    eprintln!("  → Version: {}", pkg.version().unwrap_or("unknown"));
+    if let Some(commit) = pkg.origin().and_then(|o| o.commit.as_deref()) {
+        eprintln!("  → Commit: {commit}");
+    }
+    eprintln!("  → Parsing source...");
+
+    let records = match language {
+        "R" => r_source_extractor::extract_from_source(pkg, options)?,
+        "Python" => python_source_extractor::extract_from_source(pkg, options)?,
+        _ => anyhow::bail!("Unknown language: {language}"),
+    };
+    Ok(records)
+}
+
 /// Apply post-extraction transformations based on options.
 fn apply_transformations(
     records: Vec<schema::Record>,
@@ -191,29 +747,27 @@ fn apply_transformations(
     }
 }
 
-/// Output records to stdout in the specified format
+/// Output records to `handle` in the specified format
 fn output_records(
+    handle: &mut impl Write,
     records: &[schema::Record],
     format: OutputFormat,
     pkg_name: &str,
     language: &str,
     no_header: bool,
 ) -> Result<()> {
-    let stdout = io::stdout();
-    let mut handle = stdout.lock();
-
     // Create context header if not disabled
     let header = if no_header {
         None
     } else {
         Some(schema::Record::ContextHeader(schema::ContextHeaderRecord {
-            llm_instructions: generate_llm_instructions(pkg_name, language),
+            llm_instructions: generate_llm_instructions(pkg_name, language, records),
         }))
     };
 
     match format {
-        OutputFormat::Yaml => write_yaml(&mut handle, header.as_ref(), records)?,
-        OutputFormat::Json => write_json(&mut handle, header.as_ref(), records)?,
+        OutputFormat::Yaml => write_yaml(handle, header.as_ref(), records)?,
+        OutputFormat::Json => write_json(handle, header.as_ref(), records)?,
     }
 
     Ok(())
@@ -251,13 +805,37 @@ fn write_json(
     Ok(())
 }
 
-/// Generate LLM instructions for the context header
-fn generate_llm_instructions(pkg_name: &str, language: &str) -> String {
-    format!(
+/// Generate LLM instructions for the context header, describing only the
+/// kinds of records that were emitted
+fn generate_llm_instructions(pkg_name: &str, language: &str, records: &[schema::Record]) -> String {
+    let mut instructions = format!(
         "This is an LLM-optimized API specification for the {language} package '{pkg_name}'. \
 Use this context to write correct code using {pkg_name} functions. \
-Each 'function' record describes a public function with its signature, arguments, and purpose. \
-The 'package' record contains metadata. \
-All listed functions are part of the public API."
-    )
+Each 'function' record describes a public function with its signature, arguments, and purpose. "
+    );
+    if records
+        .iter()
+        .any(|r| matches!(r, schema::Record::Generic(_)))
+    {
+        instructions.push_str(
+            "Each 'generic' record describes an S3 generic and lists its methods by class; \
+call the generic rather than its methods. ",
+        );
+    }
+    instructions.push_str(
+        "The 'package' record contains metadata. \
+All listed functions are part of the public API.",
+    );
+    // Each dependency contributes a package record of its own
+    let packages = records
+        .iter()
+        .filter(|r| matches!(r, schema::Record::Package(_)))
+        .count();
+    if packages > 1 {
+        instructions.push_str(&format!(
+            " Records carrying a 'package' field belong to that package; records of dependencies \
+are limited to what '{pkg_name}' uses."
+        ));
+    }
+    instructions
 }
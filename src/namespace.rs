@@ -0,0 +1,274 @@
+//! NAMESPACE parsing
+//!
+//! An R package's NAMESPACE lists what it exports and imports as calls to
+//! `export()`, `exportPattern()`, `S3method()`, `import()`, `importFrom()`
+//! and friends. Directives may span lines, quote or backquote their names
+//! and sit inside `if` blocks (`if (getRversion() >= "3.6.0") S3method(...)`),
+//! so the file is tokenized as R code rather than read line by line.
+
+use crate::r_lexer::{self, Spanned, Token};
+use regex::Regex;
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::Path;
+
+/// An S3 method registered with `S3method(generic, class[, function])`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct S3Method {
+    /// Generic the method is registered for, without a `pkg::` prefix
+    pub generic: String,
+    /// Class the method handles
+    pub class: String,
+    /// Function implementing the method (`generic.class` by default)
+    pub function: String,
+}
+
+/// Exports and imports declared in a NAMESPACE file
+#[derive(Debug, Default)]
+pub struct Namespace {
+    /// Names listed in `export()`
+    pub exports: BTreeSet<String>,
+    /// Regular expressions of `exportPattern()`
+    pub export_patterns: Vec<Regex>,
+    /// Registrations of `S3method()`
+    pub s3_methods: Vec<S3Method>,
+    /// S4 classes of `exportClasses()`
+    pub export_classes: BTreeSet<String>,
+    /// S4 generics of `exportMethods()`
+    pub export_methods: BTreeSet<String>,
+    /// Imported names by package; an empty list imports the whole namespace
+    pub imports: BTreeMap<String, Vec<String>>,
+    /// Whether every name is exported, as for packages without a NAMESPACE
+    /// file
+    exports_all: bool,
+}
+
+impl Namespace {
+    /// Read the NAMESPACE of the package at `path`.
+    ///
+    /// R gives packages without a NAMESPACE file one that exports every
+    /// name; an existing but empty file exports nothing.
+    pub fn read(path: &Path) -> Self {
+        match std::fs::read_to_string(path.join("NAMESPACE")) {
+            Ok(content) => Self::parse(&content),
+            Err(_) => Self {
+                exports_all: true,
+                ..Self::default()
+            },
+        }
+    }
+
+    /// Parse the content of a NAMESPACE file
+    pub fn parse(content: &str) -> Self {
+        let tokens: Vec<Spanned> = r_lexer::tokenize(content)
+            .into_iter()
+            .filter(|t| t.token != Token::Newline)
+            .collect();
+
+        let mut namespace = Self::default();
+        let mut i = 0;
+        while i < tokens.len() {
+            let directive = tokens[i].symbol();
+            let is_call = tokens.get(i + 1).is_some_and(|t| t.is_punct('('));
+            let (Some(directive), true) = (directive, is_call) else {
+                i += 1;
+                continue;
+            };
+            let (args, next) = call_arguments(&tokens, i + 2);
+            namespace.apply(directive, &args);
+            i = next;
+        }
+        namespace
+    }
+
+    /// Record one directive with its (unquoted) arguments
+    fn apply(&mut self, directive: &str, args: &[Argument]) {
+        let values = || {
+            args.iter()
+                .filter(|a| a.name.is_none())
+                .map(|a| a.value.clone())
+        };
+        match directive {
+            "export" => self.exports.extend(values()),
+            "exportPattern" => {
+                for pattern in values() {
+                    match Regex::new(&pattern) {
+                        Ok(regex) => self.export_patterns.push(regex),
+                        Err(e) => eprintln!("  → Ignoring exportPattern(\"{pattern}\"): {e}"),
+                    }
+                }
+            }
+            "exportClasses" | "exportClass" => self.export_classes.extend(values()),
+            "exportMethods" => self.export_methods.extend(values()),
+            "S3method" => {
+                let mut values = values();
+                if let (Some(generic), Some(class)) = (values.next(), values.next()) {
+                    // Delayed registration names the generic's package
+                    let generic = generic.rsplit("::").next().unwrap_or(&generic).to_string();
+                    let function = values
+                        .next()
+                        .unwrap_or_else(|| format!("{generic}.{class}"));
+                    self.s3_methods.push(S3Method {
+                        generic,
+                        class,
+                        function,
+                    });
+                }
+            }
+            "import" => {
+                // `except =` only narrows the import
+                for package in values() {
+                    self.imports.entry(package).or_default();
+                }
+            }
+            "importFrom" | "importClassesFrom" | "importMethodsFrom" => {
+                let mut values = values();
+                if let Some(package) = values.next() {
+                    self.imports.entry(package).or_default().extend(values);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Whether the function `name` can be called by users of the package:
+    /// it is exported by name or pattern, or registered as an S3 method
+    /// (reachable through its generic)
+    pub fn is_exported(&self, name: &str) -> bool {
+        self.exports_all
+            || self.exports.contains(name)
+            || self.export_methods.contains(name)
+            || self.export_patterns.iter().any(|p| p.is_match(name))
+            || self.s3_methods.iter().any(|m| m.function == name)
+    }
+}
+
+/// An argument of a directive call
+struct Argument {
+    /// Name of a `name = value` argument
+    name: Option<String>,
+    /// Symbol or string value; other expressions are kept as their first token
+    value: String,
+}
+
+/// Arguments of the call whose first argument token is at `start`, and the
+/// position after its closing parenthesis
+fn call_arguments(tokens: &[Spanned], start: usize) -> (Vec<Argument>, usize) {
+    let mut args = Vec::new();
+    let mut current: Vec<&Token> = Vec::new();
+    let mut depth = 0usize;
+    let mut i = start;
+    while i < tokens.len() {
+        let token = &tokens[i];
+        i += 1;
+        match token.token {
+            Token::Punct('(' | '[' | '{') => depth += 1,
+            Token::Punct(')' | ']' | '}') if depth > 0 => depth -= 1,
+            Token::Punct(')') => break,
+            Token::Punct(',') if depth == 0 => {
+                args.extend(argument(&current));
+                current.clear();
+                continue;
+            }
+            _ => {}
+        }
+        current.push(&token.token);
+    }
+    args.extend(argument(&current));
+    (args, i)
+}
+
+fn argument(tokens: &[&Token]) -> Option<Argument> {
+    let text = |token: &Token| match token {
+        Token::Symbol(s) | Token::Str(s) | Token::Number(s) => Some(s.clone()),
+        _ => None,
+    };
+    match tokens {
+        [name, Token::Op(op), value, ..] if op == "=" => Some(Argument {
+            name: text(name),
+            value: text(value)?,
+        }),
+        // `pkg::generic` in S3method()
+        [Token::Symbol(package), Token::Op(op), Token::Symbol(name)] if op == "::" => {
+            Some(Argument {
+                name: None,
+                value: format!("{package}::{name}"),
+            })
+        }
+        [first, ..] => Some(Argument {
+            name: None,
+            value: text(first)?,
+        }),
+        [] => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_roxygen_namespace() {
+        let namespace = Namespace::parse(
+            r#"# Generated by roxygen2: do not edit by hand
+
+S3method("[",tbl_df)
+S3method(format,tbl_df)
+S3method(print, tbl_df, print_tbl)
+if (getRversion() >= "3.6.0") {
+  S3method(vctrs::vec_ptype2, tbl_df)
+}
+export("%>%")
+export(`names<-.tbl`)
+export(
+  as_tibble,
+  tibble
+)
+exportClasses(Tbl)
+exportMethods(show)
+exportPattern("^[^\\.]")
+import(rlang, except = c(abort))
+importFrom(magrittr,"%>%")
+importFrom(pillar, pillar_shaft, type_sum)
+useDynLib(tibble, .registration = TRUE)
+"#,
+        );
+
+        assert_eq!(
+            namespace.exports.iter().collect::<Vec<_>>(),
+            ["%>%", "as_tibble", "names<-.tbl", "tibble"]
+        );
+        assert_eq!(
+            namespace.s3_methods,
+            [
+                ("[", "tbl_df", "[.tbl_df"),
+                ("format", "tbl_df", "format.tbl_df"),
+                ("print", "tbl_df", "print_tbl"),
+                ("vec_ptype2", "tbl_df", "vec_ptype2.tbl_df"),
+            ]
+            .map(|(generic, class, function)| S3Method {
+                generic: generic.to_string(),
+                class: class.to_string(),
+                function: function.to_string(),
+            })
+        );
+        assert!(namespace.export_classes.contains("Tbl"));
+        assert!(namespace.is_exported("show"));
+        assert!(namespace.is_exported("print_tbl"));
+        assert!(namespace.is_exported("new_tibble"));
+        assert!(!namespace.is_exported(".internal"));
+
+        assert_eq!(namespace.imports["rlang"], Vec::<String>::new());
+        assert_eq!(namespace.imports["magrittr"], ["%>%"]);
+        assert_eq!(namespace.imports["pillar"], ["pillar_shaft", "type_sum"]);
+        assert!(!namespace.imports.contains_key("tibble"));
+    }
+
+    #[test]
+    fn test_empty_namespace_exports_nothing() {
+        assert!(!Namespace::parse("").is_exported("f"));
+        assert!(!Namespace::parse("importFrom(stats, sd)\n").is_exported("sd"));
+
+        let dir = tempfile::TempDir::new().unwrap();
+        assert!(Namespace::read(dir.path()).is_exported("f"));
+    }
+}
@@ -0,0 +1,692 @@
+//! Dependencies of an analysis project
+//!
+//! An R project lists its packages in an `renv.lock` (exact versions and
+//! sources) or in the Depends/Imports/LinkingTo fields of a DESCRIPTION file.
+//! A Python project lists them in a `uv.lock`, `poetry.lock`,
+//! `requirements.txt` or the `[project.dependencies]` of a pyproject.toml.
+//! The project itself is not a package: only its dependencies are extracted.
+//! Each dependency is turned into a package specifier, so that it is fetched
+//! exactly like a package named on the command line.
+
+use crate::dcf;
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// Packages that ship with R and cannot be fetched from a repository
+const BASE_PACKAGES: &[&str] = &[
+    "base",
+    "compiler",
+    "datasets",
+    "graphics",
+    "grDevices",
+    "grid",
+    "methods",
+    "parallel",
+    "splines",
+    "stats",
+    "stats4",
+    "tcltk",
+    "tools",
+    "utils",
+];
+
+/// A package the project depends on
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Dependency {
+    /// Package name
+    pub name: String,
+    /// Version the project pins, if any
+    pub version: Option<String>,
+    /// Package specifier to fetch it with (e.g. "dplyr@1.1.4", "bioc:limma")
+    pub spec: String,
+}
+
+/// Index of the context files written for a project
+#[derive(Debug, Serialize)]
+pub struct ProjectIndex {
+    /// File the dependencies were read from
+    pub project: String,
+    pub packages: Vec<IndexEntry>,
+    /// Dependencies that could not be fetched or extracted
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub failed: Vec<FailedEntry>,
+}
+
+/// A dependency that could not be processed
+#[derive(Debug, Serialize)]
+pub struct FailedEntry {
+    pub name: String,
+    pub source: String,
+    pub error: String,
+}
+
+/// One package of a project index
+#[derive(Debug, Serialize)]
+pub struct IndexEntry {
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+    /// Package specifier the package was fetched with
+    pub source: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub commit: Option<String>,
+    /// Context file, relative to the index
+    pub file: String,
+}
+
+/// Find the dependency file of an R project: `path` itself, or the
+/// `renv.lock` (preferred) or DESCRIPTION of a project directory
+pub fn find_r_project_file(path: &Path) -> Result<PathBuf> {
+    if !path.is_dir() {
+        return Ok(path.to_path_buf());
+    }
+    ["renv.lock", "DESCRIPTION"]
+        .iter()
+        .map(|name| path.join(name))
+        .find(|file| file.is_file())
+        .with_context(|| format!("No renv.lock or DESCRIPTION in {}", path.display()))
+}
+
+/// Read the dependencies of an R project from an `renv.lock` or DESCRIPTION
+pub fn read_r_dependencies(file: &Path) -> Result<Vec<Dependency>> {
+    let content = std::fs::read_to_string(file)
+        .with_context(|| format!("Failed to read {}", file.display()))?;
+
+    let is_lockfile =
+        file.extension().is_some_and(|e| e == "lock") || content.trim_start().starts_with('{');
+    if is_lockfile {
+        parse_renv_lock(&content).with_context(|| format!("Invalid lockfile {}", file.display()))
+    } else {
+        Ok(parse_description(&content))
+    }
+}
+
+/// Dependencies recorded in an `renv.lock`, with their pinned versions and
+/// sources. Packages from sources pkgctx cannot fetch are skipped with a
+/// warning.
+fn parse_renv_lock(content: &str) -> Result<Vec<Dependency>> {
+    let lock: serde_json::Value = serde_json::from_str(content)?;
+    let packages = lock["Packages"]
+        .as_object()
+        .context("No 'Packages' entry")?;
+    // Bioconductor packages come from the release the project was locked with
+    let bioc_release = lock["Bioconductor"]["Version"]
+        .as_str()
+        .map_or(String::new(), |release| format!("@{release}"));
+
+    let mut dependencies = Vec::new();
+    for (name, record) in packages {
+        let field = |key: &str| record[key].as_str().filter(|v| !v.is_empty());
+        let version = field("Version").map(str::to_string);
+        let at_sha = field("RemoteSha").map_or(String::new(), |sha| format!("@{sha}"));
+        let subdir = field("RemoteSubdir").map_or(String::new(), |dir| format!("/{dir}"));
+
+        let spec = match field("Source").unwrap_or("Repository") {
+            "Repository" | "CRAN" => {
+                let repository = field("RemoteRepos").or(field("Repository"));
+                match repository.and_then(runiverse_owner) {
+                    Some(owner) => format!("runiverse:{owner}/{name}"),
+                    None => match &version {
+                        Some(version) => format!("{name}@{version}"),
+                        None => name.clone(),
+                    },
+                }
+            }
+            "Bioconductor" => format!("bioc:{name}{bioc_release}"),
+            source @ ("GitHub" | "GitLab") => {
+                let (prefix, default_host) = match source {
+                    "GitHub" => ("github", "api.github.com"),
+                    _ => ("gitlab", "gitlab.com"),
+                };
+                let host = field("RemoteHost").unwrap_or(default_host);
+                let (Some(user), Some(repo)) = (field("RemoteUsername"), field("RemoteRepo"))
+                else {
+                    eprintln!("  → Skipping {name}: incomplete {source} remote in lockfile");
+                    continue;
+                };
+                if host != default_host {
+                    eprintln!("  → Skipping {name}: {source} host {host} is not supported");
+                    continue;
+                }
+                format!("{prefix}:{user}/{repo}{subdir}{at_sha}")
+            }
+            "git" => {
+                let Some(url) = field("RemoteUrl") else {
+                    eprintln!("  → Skipping {name}: git remote without a URL in lockfile");
+                    continue;
+                };
+                let mut spec = format!("git+{url}{at_sha}");
+                if let Some(dir) = field("RemoteSubdir") {
+                    spec.push_str(&format!("#subdirectory={dir}"));
+                }
+                spec
+            }
+            other => {
+                eprintln!("  → Skipping {name}: packages from '{other}' sources cannot be fetched");
+                continue;
+            }
+        };
+
+        dependencies.push(Dependency {
+            name: name.clone(),
+            version,
+            spec,
+        });
+    }
+    Ok(dependencies)
+}
+
+/// Owner of an r-universe repository URL (`https://{owner}.r-universe.dev`)
+fn runiverse_owner(url: &str) -> Option<&str> {
+    let host = url.strip_prefix("https://")?.trim_end_matches('/');
+    host.strip_suffix(".r-universe.dev")
+        .filter(|owner| !owner.is_empty() && !owner.contains(['/', '.']))
+}
+
+/// Dependencies declared in the Depends, Imports and LinkingTo fields of a
+/// DESCRIPTION file
+fn parse_description(content: &str) -> Vec<Dependency> {
+    description_dependencies(content, &["Depends", "Imports", "LinkingTo"])
+}
+
+/// Dependencies declared in the given `fields` of a DESCRIPTION file,
+/// without R itself and its base packages. Only `(== x.y.z)` constraints pin
+/// a version; packages listed under Remotes are fetched from there instead
+/// of CRAN.
+pub fn description_dependencies(content: &str, fields: &[&str]) -> Vec<Dependency> {
+    let description = dcf::parse(content).into_iter().next().unwrap_or_default();
+    let remotes = description
+        .get("Remotes")
+        .map(|value| parse_remotes(value))
+        .unwrap_or_default();
+
+    let mut dependencies: Vec<Dependency> = Vec::new();
+    for field in fields {
+        let Some(value) = description.get(*field) else {
+            continue;
+        };
+        for (name, constraint) in parse_package_list(value) {
+            if name == "R"
+                || BASE_PACKAGES.contains(&name.as_str())
+                || dependencies.iter().any(|d| d.name == name)
+            {
+                continue;
+            }
+            let version =
+                constraint.and_then(|c| c.strip_prefix("==").map(|v| v.trim().to_string()));
+            let spec = match (remotes.get(&name), &version) {
+                (Some(remote), _) => remote.clone(),
+                (None, Some(version)) => format!("{name}@{version}"),
+                (None, None) => name.clone(),
+            };
+            dependencies.push(Dependency {
+                name,
+                version,
+                spec,
+            });
+        }
+    }
+    dependencies
+}
+
+/// Split a DESCRIPTION package list ("dplyr (>= 1.0), tidyr") into names and
+/// version constraints
+fn parse_package_list(value: &str) -> Vec<(String, Option<String>)> {
+    dcf::split_list(value)
+        .into_iter()
+        .filter_map(|entry| {
+            let (name, constraint) = match entry.split_once('(') {
+                Some((name, rest)) => (name.trim(), Some(rest.trim_end_matches(')').trim())),
+                None => (entry.as_str(), None),
+            };
+            (!name.is_empty()).then(|| (name.to_string(), constraint.map(str::to_string)))
+        })
+        .collect()
+}
+
+/// Package specifiers for the entries of a Remotes field, keyed by the
+/// package name they are expected to provide (the repository or
+/// subdirectory name)
+fn parse_remotes(value: &str) -> BTreeMap<String, String> {
+    let mut remotes = BTreeMap::new();
+    for remote in value.split(',').map(str::trim).filter(|r| !r.is_empty()) {
+        let (kind, target) = remote.split_once("::").unwrap_or(("github", remote));
+        let spec = match kind {
+            "github" | "gitlab" | "codeberg" => format!("{kind}:{target}"),
+            // `bioc::3.18/limma` pins a release; `release`/`devel` do not
+            "bioc" => match target.split_once('/') {
+                Some((release, name)) if release.starts_with(|c: char| c.is_ascii_digit()) => {
+                    format!("bioc:{name}@{release}")
+                }
+                _ => format!("bioc:{}", target.rsplit('/').next().unwrap_or(target)),
+            },
+            "git" => format!("git+{target}"),
+            _ => {
+                eprintln!("  → Ignoring unsupported remote '{remote}'");
+                continue;
+            }
+        };
+        let path = target.split('@').next().unwrap_or(target);
+        let name = path
+            .trim_end_matches('/')
+            .rsplit('/')
+            .next()
+            .unwrap_or(path)
+            .trim_end_matches(".git");
+        remotes.insert(name.to_string(), spec);
+    }
+    remotes
+}
+
+/// Find the dependency file of a Python project: `path` itself, or the
+/// lockfile, requirements.txt or pyproject.toml of a project directory (in
+/// that order)
+pub fn find_python_project_file(path: &Path) -> Result<PathBuf> {
+    if !path.is_dir() {
+        return Ok(path.to_path_buf());
+    }
+    [
+        "uv.lock",
+        "poetry.lock",
+        "requirements.txt",
+        "pyproject.toml",
+    ]
+    .iter()
+    .map(|name| path.join(name))
+    .find(|file| file.is_file())
+    .with_context(|| {
+        format!(
+            "No uv.lock, poetry.lock, requirements.txt or pyproject.toml in {}",
+            path.display()
+        )
+    })
+}
+
+/// Read the dependencies of a Python project from a lockfile, requirements
+/// file or pyproject.toml
+pub fn read_python_dependencies(file: &Path) -> Result<Vec<Dependency>> {
+    let content = std::fs::read_to_string(file)
+        .with_context(|| format!("Failed to read {}", file.display()))?;
+    let file_name = file.file_name().and_then(|n| n.to_str()).unwrap_or("");
+
+    let parsed = match file_name {
+        "uv.lock" | "poetry.lock" => parse_python_lock(&content),
+        "pyproject.toml" => parse_pyproject_dependencies(&content),
+        _ => {
+            let base_dir = file.parent().unwrap_or(Path::new("."));
+            parse_requirements(&content, base_dir, &mut vec![file.to_path_buf()])
+        }
+    };
+    parsed.with_context(|| format!("Invalid dependency file {}", file.display()))
+}
+
+/// Dependencies of a requirements file, following `-r` includes
+fn parse_requirements(
+    content: &str,
+    base_dir: &Path,
+    seen: &mut Vec<PathBuf>,
+) -> Result<Vec<Dependency>> {
+    let mut dependencies = Vec::new();
+    let mut logical_line = String::new();
+    for line in content.lines() {
+        // Backslash continues a requirement on the next line
+        if let Some(start) = line.strip_suffix('\\') {
+            logical_line.push_str(start);
+            continue;
+        }
+        logical_line.push_str(line);
+        let line = std::mem::take(&mut logical_line);
+
+        // Comments start at a '#' at the line start or after whitespace
+        let line = match line
+            .find(" #")
+            .or_else(|| line.starts_with('#').then_some(0))
+        {
+            Some(i) => line[..i].trim().to_string(),
+            None => line.trim().to_string(),
+        };
+        if line.is_empty() {
+            continue;
+        }
+
+        if let Some(include) = line
+            .strip_prefix("-r ")
+            .or_else(|| line.strip_prefix("--requirement "))
+        {
+            let path = base_dir.join(include.trim());
+            if seen.contains(&path) {
+                continue;
+            }
+            seen.push(path.clone());
+            let content = std::fs::read_to_string(&path)
+                .with_context(|| format!("Failed to read {}", path.display()))?;
+            let base_dir = path.parent().unwrap_or(base_dir).to_path_buf();
+            dependencies.extend(parse_requirements(&content, &base_dir, seen)?);
+            continue;
+        }
+        if line.starts_with('-') {
+            // Index options, constraints files and editable installs
+            continue;
+        }
+        dependencies.extend(parse_requirement(&line));
+    }
+    Ok(dependencies)
+}
+
+/// Dependencies in the `[project.dependencies]` of a pyproject.toml
+pub fn parse_pyproject_dependencies(content: &str) -> Result<Vec<Dependency>> {
+    let pyproject: toml::Table = content.parse()?;
+    let requirements = pyproject
+        .get("project")
+        .and_then(|p| p.get("dependencies"))
+        .and_then(toml::Value::as_array)
+        .map(Vec::as_slice)
+        .unwrap_or_default();
+    Ok(requirements
+        .iter()
+        .filter_map(toml::Value::as_str)
+        .filter_map(parse_requirement)
+        .collect())
+}
+
+/// Parse a PEP 508 requirement (`name[extras] (specifiers) ; markers` or
+/// `name @ url`). Only `==`/`===` pin a version.
+pub fn parse_requirement(requirement: &str) -> Option<Dependency> {
+    let requirement = requirement.split(';').next()?.trim();
+    let name_end = requirement
+        .find(|c: char| !(c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.')))
+        .unwrap_or(requirement.len());
+    let name = &requirement[..name_end];
+    if name.is_empty() {
+        return None;
+    }
+
+    let mut rest = requirement[name_end..].trim_start();
+    if rest.starts_with('[') {
+        rest = rest.split_once(']').map_or("", |(_, r)| r).trim_start();
+    }
+
+    // Direct references: only git URLs can be fetched
+    if let Some(url) = rest.strip_prefix('@') {
+        return Some(Dependency {
+            name: name.to_string(),
+            version: None,
+            spec: pip_git_spec(url.trim()),
+        });
+    }
+
+    let version = rest
+        .trim_matches(|c| c == '(' || c == ')')
+        .split(',')
+        .map(str::trim)
+        .find_map(|specifier| {
+            let version = specifier
+                .strip_prefix("===")
+                .or_else(|| specifier.strip_prefix("=="))?
+                .trim();
+            (!version.contains('*')).then(|| version.to_string())
+        });
+    let spec = match &version {
+        Some(version) => format!("{name}=={version}"),
+        None => name.to_string(),
+    };
+    Some(Dependency {
+        name: name.to_string(),
+        version,
+        spec,
+    })
+}
+
+/// Specifier for a pip VCS URL (`git+https://…@rev#egg=x&subdirectory=y`):
+/// of the fragment, only the subdirectory is kept
+fn pip_git_spec(url: &str) -> String {
+    let Some((base, fragment)) = url.split_once('#') else {
+        return url.to_string();
+    };
+    match fragment
+        .split('&')
+        .find_map(|param| param.strip_prefix("subdirectory="))
+    {
+        Some(subdir) => format!("{base}#subdirectory={subdir}"),
+        None => base.to_string(),
+    }
+}
+
+/// Every package locked in a `uv.lock` or `poetry.lock`, at its locked
+/// version or commit. The project itself and path dependencies are skipped.
+fn parse_python_lock(content: &str) -> Result<Vec<Dependency>> {
+    let lock: toml::Table = content.parse()?;
+    let packages = lock
+        .get("package")
+        .and_then(toml::Value::as_array)
+        .context("No [[package]] entries")?;
+
+    let mut dependencies = Vec::new();
+    for package in packages {
+        let name = package.get("name").and_then(toml::Value::as_str);
+        let version = package.get("version").and_then(toml::Value::as_str);
+        let (Some(name), Some(version)) = (name, version) else {
+            continue;
+        };
+        let source = package.get("source");
+        let field = |key: &str| {
+            source
+                .and_then(|s| s.get(key))
+                .and_then(toml::Value::as_str)
+        };
+
+        let spec = if let Some(git) = field("git") {
+            // uv: "https://host/repo?subdirectory=x&rev=main#<commit>"
+            let (url, commit) = git.split_once('#').unwrap_or((git, ""));
+            let (url, query) = url.split_once('?').unwrap_or((url, ""));
+            let subdir = query
+                .split('&')
+                .find_map(|param| param.strip_prefix("subdirectory="));
+            git_spec(url, Some(commit), subdir)
+        } else if field("type") == Some("git") {
+            // poetry: [package.source] type = "git", url, resolved_reference
+            let Some(url) = field("url") else {
+                continue;
+            };
+            git_spec(
+                url,
+                field("resolved_reference").or(field("reference")),
+                field("subdirectory"),
+            )
+        } else if ["virtual", "editable", "directory", "path"]
+            .iter()
+            .any(|key| field(key).is_some())
+            || matches!(field("type"), Some("directory" | "file"))
+        {
+            continue;
+        } else {
+            format!("{name}=={version}")
+        };
+
+        dependencies.push(Dependency {
+            name: name.to_string(),
+            version: Some(version.to_string()),
+            spec,
+        });
+    }
+    Ok(dependencies)
+}
+
+/// `git+url@rev#subdirectory=dir` specifier
+fn git_spec(url: &str, rev: Option<&str>, subdir: Option<&str>) -> String {
+    let mut spec = format!("git+{url}");
+    if let Some(rev) = rev.filter(|r| !r.is_empty()) {
+        spec.push_str(&format!("@{rev}"));
+    }
+    if let Some(subdir) = subdir {
+        spec.push_str(&format!("#subdirectory={subdir}"));
+    }
+    spec
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_renv_lock() {
+        let lock = r#"{
+          "R": {"Version": "4.3.2"},
+          "Bioconductor": {"Version": "3.18"},
+          "Packages": {
+            "dplyr": {"Package": "dplyr", "Version": "1.1.4", "Source": "Repository", "Repository": "CRAN"},
+            "limma": {"Package": "limma", "Version": "3.58.1", "Source": "Bioconductor"},
+            "rix": {"Package": "rix", "Version": "0.9.0", "Source": "GitHub",
+                    "RemoteUsername": "ropensci", "RemoteRepo": "rix", "RemoteSha": "3f2c1ab0"},
+            "arrow": {"Package": "arrow", "Version": "15.0.0", "Source": "GitHub",
+                      "RemoteUsername": "apache", "RemoteRepo": "arrow", "RemoteSubdir": "r",
+                      "RemoteSha": "abc1234"},
+            "targets": {"Package": "targets", "Version": "1.4.0", "Source": "Repository",
+                        "Repository": "https://ropensci.r-universe.dev"},
+            "mine": {"Package": "mine", "Version": "0.1", "Source": "Local"}
+          }
+        }"#;
+        let specs: Vec<(String, String)> = parse_renv_lock(lock)
+            .unwrap()
+            .into_iter()
+            .map(|d| (d.name, d.spec))
+            .collect();
+        let expected = [
+            ("arrow", "github:apache/arrow/r@abc1234"),
+            ("dplyr", "dplyr@1.1.4"),
+            ("limma", "bioc:limma@3.18"),
+            ("rix", "github:ropensci/rix@3f2c1ab0"),
+            ("targets", "runiverse:ropensci/targets"),
+        ];
+        assert_eq!(specs, expected.map(|(n, s)| (n.to_string(), s.to_string())));
+    }
+
+    #[test]
+    fn test_parse_description_dependencies() {
+        let description = "\
+Type: Project
+Depends: R (>= 4.1), stats
+Imports:
+    dplyr (>= 1.1.0),
+    data.table (== 1.15.0),
+    rix
+LinkingTo: Rcpp, dplyr
+Remotes: ropensci/rix@main, bioc::release/limma, bioc::3.18/Rcpp
+";
+        let dependencies = parse_description(description);
+        let specs: Vec<&str> = dependencies.iter().map(|d| d.spec.as_str()).collect();
+        assert_eq!(
+            specs,
+            [
+                "dplyr",
+                "data.table@1.15.0",
+                "github:ropensci/rix@main",
+                "bioc:Rcpp@3.18"
+            ]
+        );
+        assert_eq!(dependencies[1].version.as_deref(), Some("1.15.0"));
+    }
+
+    #[test]
+    fn test_parse_requirements() {
+        let dir = tempfile::TempDir::new().unwrap();
+        std::fs::write(dir.path().join("base.txt"), "numpy==1.26.4\n").unwrap();
+        let content = "\
+# pinned
+-r base.txt
+--index-url https://pypi.example/simple
+requests[socks]==2.31.0 ; python_version >= '3.8'  # http
+pandas>=2.0
+mylib @ git+https://github.com/o/mylib.git@v1.0#egg=mylib&subdirectory=py
+-e ./local
+";
+        let dependencies = parse_requirements(content, dir.path(), &mut Vec::new()).unwrap();
+        let specs: Vec<&str> = dependencies.iter().map(|d| d.spec.as_str()).collect();
+        assert_eq!(
+            specs,
+            [
+                "numpy==1.26.4",
+                "requests==2.31.0",
+                "pandas",
+                "git+https://github.com/o/mylib.git@v1.0#subdirectory=py"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_python_lockfiles() {
+        let uv_lock = r#"
+version = 1
+
+[[package]]
+name = "myproject"
+version = "0.1.0"
+source = { virtual = "." }
+
+[[package]]
+name = "idna"
+version = "3.7"
+source = { registry = "https://pypi.org/simple" }
+
+[[package]]
+name = "tool"
+version = "0.2.0"
+source = { git = "https://github.com/o/tool?subdirectory=py&rev=main#0123abcd" }
+"#;
+        let specs: Vec<String> = parse_python_lock(uv_lock)
+            .unwrap()
+            .into_iter()
+            .map(|d| d.spec)
+            .collect();
+        assert_eq!(
+            specs,
+            [
+                "idna==3.7",
+                "git+https://github.com/o/tool@0123abcd#subdirectory=py"
+            ]
+        );
+
+        let poetry_lock = r#"
+[[package]]
+name = "certifi"
+version = "2024.2.2"
+description = "Root certificates"
+
+[[package]]
+name = "vendored"
+version = "1.0"
+
+[package.source]
+type = "git"
+url = "https://example.org/vendored.git"
+reference = "main"
+resolved_reference = "fedcba98"
+"#;
+        let specs: Vec<String> = parse_python_lock(poetry_lock)
+            .unwrap()
+            .into_iter()
+            .map(|d| d.spec)
+            .collect();
+        assert_eq!(
+            specs,
+            [
+                "certifi==2024.2.2",
+                "git+https://example.org/vendored.git@fedcba98"
+            ]
+        );
+
+        let pyproject = r#"
+[project]
+name = "analysis"
+dependencies = ["httpx==0.27.0", "rich>=13"]
+"#;
+        let specs: Vec<String> = parse_pyproject_dependencies(pyproject)
+            .unwrap()
+            .into_iter()
+            .map(|d| d.spec)
+            .collect();
+        assert_eq!(specs, ["httpx==0.27.0", "rich"]);
+    }
+}
@@ -0,0 +1,555 @@
+//! Python project metadata
+//!
+//! Reads the name, version and requirements of a Python project from, in
+//! order of precedence: the `[project]` table of pyproject.toml (or
+//! `[tool.poetry]`), setup.cfg, the core metadata of a distribution
+//! (`PKG-INFO` of an sdist, `*.dist-info/METADATA` of a wheel) and literal
+//! arguments in setup.py. Each field is taken from the first source that
+//! declares it, so a `dynamic` field of pyproject.toml is filled from the
+//! others. A dynamic version is otherwise resolved from the `__version__`
+//! of the package (or its `_version.py`).
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// Metadata of a Python project; every field is optional
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct PythonMetadata {
+    pub name: Option<String>,
+    pub version: Option<String>,
+    /// One-line summary (`description` in pyproject.toml)
+    pub summary: Option<String>,
+    pub license: Option<String>,
+    pub urls: Vec<String>,
+    /// Supported Python versions (e.g. ">=3.8")
+    pub requires_python: Option<String>,
+    /// PEP 508 requirements, without those of optional extras
+    pub dependencies: Vec<String>,
+    /// Trove classifiers
+    pub classifiers: Vec<String>,
+}
+
+impl PythonMetadata {
+    /// Fill the fields not yet known from `other`
+    fn fill(&mut self, other: Self) {
+        self.name = self.name.take().or(other.name);
+        self.version = self.version.take().or(other.version);
+        self.summary = self.summary.take().or(other.summary);
+        self.license = self.license.take().or(other.license);
+        self.requires_python = self.requires_python.take().or(other.requires_python);
+        for (field, value) in [
+            (&mut self.urls, other.urls),
+            (&mut self.dependencies, other.dependencies),
+            (&mut self.classifiers, other.classifiers),
+        ] {
+            if field.is_empty() {
+                *field = value;
+            }
+        }
+    }
+}
+
+/// Read the metadata of the Python project at `path`
+pub fn read(path: &Path) -> PythonMetadata {
+    let read = |file: &str| std::fs::read_to_string(path.join(file)).ok();
+
+    let mut metadata = PythonMetadata::default();
+    let mut version_attr = None;
+    if let Some((pyproject, attr)) = read("pyproject.toml").and_then(|c| parse_pyproject(&c)) {
+        metadata.fill(pyproject);
+        version_attr = attr;
+    }
+    if let Some((setup_cfg, attr)) = read("setup.cfg").map(|c| parse_setup_cfg(&c)) {
+        metadata.fill(setup_cfg);
+        version_attr = version_attr.or(attr);
+    }
+    if let Some(core) = core_metadata_file(path).and_then(|f| std::fs::read_to_string(f).ok()) {
+        metadata.fill(parse_core_metadata(&core));
+    }
+    if let Some(setup_py) = read("setup.py") {
+        metadata.fill(parse_setup_py(&setup_py));
+    }
+
+    if metadata.version.is_none() {
+        metadata.version = match version_attr {
+            Some(VersionSource::Attr(attr)) => attribute_version(path, &attr),
+            Some(VersionSource::File(file)) => std::fs::read_to_string(path.join(file))
+                .ok()
+                .map(|v| v.trim().to_string())
+                .filter(|v| !v.is_empty()),
+            None => None,
+        }
+        .or_else(|| {
+            let name = metadata.name.as_deref()?;
+            package_version(path, &name.to_lowercase().replace(['-', '.'], "_"))
+        });
+    }
+    metadata
+}
+
+/// Where a dynamic version is declared
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum VersionSource {
+    /// A module attribute (`pkg.__version__`)
+    Attr(String),
+    /// A file holding the version, relative to the project root
+    File(String),
+}
+
+/// Core metadata of a distribution: `PKG-INFO` of an sdist or the
+/// `METADATA` of a wheel's `.dist-info`
+pub fn core_metadata_file(path: &Path) -> Option<PathBuf> {
+    let pkg_info = path.join("PKG-INFO");
+    if pkg_info.is_file() {
+        return Some(pkg_info);
+    }
+
+    std::fs::read_dir(path)
+        .ok()?
+        .filter_map(Result::ok)
+        .map(|e| e.path())
+        .filter(|p| p.extension().is_some_and(|e| e == "dist-info"))
+        .map(|p| p.join("METADATA"))
+        .find(|p| p.is_file())
+}
+
+/// Metadata in pyproject.toml, and where a dynamic version comes from
+fn parse_pyproject(content: &str) -> Option<(PythonMetadata, Option<VersionSource>)> {
+    let pyproject: toml::Table = content.parse().ok()?;
+    let string = |table: Option<&toml::Value>, key: &str| {
+        table
+            .and_then(|t| t.get(key))
+            .and_then(toml::Value::as_str)
+            .map(str::to_string)
+    };
+    let strings = |table: Option<&toml::Value>, key: &str| -> Vec<String> {
+        table
+            .and_then(|t| t.get(key))
+            .and_then(toml::Value::as_array)
+            .map(|values| {
+                values
+                    .iter()
+                    .filter_map(toml::Value::as_str)
+                    .map(str::to_string)
+                    .collect()
+            })
+            .unwrap_or_default()
+    };
+    let tool = pyproject.get("tool");
+
+    let project = pyproject.get("project");
+    let mut metadata = PythonMetadata {
+        name: string(project, "name"),
+        version: string(project, "version"),
+        summary: string(project, "description"),
+        // `license = "MIT"` (PEP 639) or `license = { text = "MIT" }`
+        license: project
+            .and_then(|p| p.get("license"))
+            .and_then(|l| l.as_str().or_else(|| l.get("text")?.as_str()))
+            .map(str::to_string),
+        urls: project
+            .and_then(|p| p.get("urls"))
+            .and_then(toml::Value::as_table)
+            .map(|urls| {
+                urls.values()
+                    .filter_map(toml::Value::as_str)
+                    .map(str::to_string)
+                    .collect()
+            })
+            .unwrap_or_default(),
+        requires_python: string(project, "requires-python"),
+        dependencies: strings(project, "dependencies"),
+        classifiers: strings(project, "classifiers"),
+    };
+
+    // Poetry (before 2.0) keeps the metadata in its own table, with
+    // dependencies as a table of constraints
+    let poetry = tool.and_then(|t| t.get("poetry"));
+    if project.is_none() && poetry.is_some() {
+        let dependencies = poetry
+            .and_then(|p| p.get("dependencies"))
+            .and_then(toml::Value::as_table);
+        let constraint = |value: &toml::Value| {
+            value
+                .as_str()
+                .or_else(|| value.get("version")?.as_str())
+                .map(str::to_string)
+        };
+        metadata.fill(PythonMetadata {
+            name: string(poetry, "name"),
+            version: string(poetry, "version"),
+            summary: string(poetry, "description"),
+            license: string(poetry, "license"),
+            urls: ["homepage", "repository", "documentation"]
+                .iter()
+                .filter_map(|key| string(poetry, key))
+                .collect(),
+            requires_python: dependencies
+                .and_then(|d| d.get("python"))
+                .and_then(constraint)
+                .map(|c| poetry_constraint(&c)),
+            dependencies: dependencies
+                .into_iter()
+                .flatten()
+                .filter(|(name, value)| {
+                    *name != "python"
+                        && value.get("optional").and_then(toml::Value::as_bool) != Some(true)
+                })
+                .map(|(name, value)| match constraint(value) {
+                    Some(c) if c != "*" => format!("{name}{}", poetry_constraint(&c)),
+                    _ => name.clone(),
+                })
+                .collect(),
+            classifiers: strings(poetry, "classifiers"),
+        });
+    }
+
+    // setuptools names an attribute, Hatch a file and Flit a module that
+    // assign `__version__`
+    let setting = |path: &[&str]| {
+        path.iter()
+            .try_fold(tool?, |value, key| value.get(key))
+            .and_then(toml::Value::as_str)
+    };
+    let version_source = setting(&["setuptools", "dynamic", "version", "attr"])
+        .map(|attr| VersionSource::Attr(attr.to_string()))
+        .or_else(|| {
+            setting(&["hatch", "version", "path"])
+                .map(|path| VersionSource::Attr(format!("{path}:__version__")))
+        })
+        .or_else(|| {
+            setting(&["flit", "module", "name"])
+                .map(|module| VersionSource::Attr(format!("{module}.__version__")))
+        });
+    Some((metadata, version_source))
+}
+
+/// Turn a Poetry constraint into a PEP 440 specifier (`^1.2` → `>=1.2,<2.0`,
+/// `~1.2` → `~=1.2`, `1.2` → `==1.2`)
+fn poetry_constraint(constraint: &str) -> String {
+    let constraint = constraint.trim();
+    if let Some(version) = constraint.strip_prefix('^') {
+        let parts: Vec<u64> = version.split('.').map_while(|p| p.parse().ok()).collect();
+        // The first non-zero component may not change
+        let bump = parts
+            .iter()
+            .position(|&p| p != 0)
+            .unwrap_or(parts.len().saturating_sub(1));
+        let upper: Vec<String> = (0..parts.len().max(bump + 1))
+            .map(|i| match i.cmp(&bump) {
+                std::cmp::Ordering::Less => parts[i].to_string(),
+                std::cmp::Ordering::Equal => (parts.get(i).copied().unwrap_or(0) + 1).to_string(),
+                std::cmp::Ordering::Greater => "0".to_string(),
+            })
+            .collect();
+        return format!(">={version},<{}", upper.join("."));
+    }
+    if let Some(version) = constraint.strip_prefix('~').filter(|v| !v.starts_with('=')) {
+        return format!("~={version}");
+    }
+    if constraint.starts_with(|c: char| c.is_ascii_digit()) {
+        return format!("=={constraint}");
+    }
+    constraint.to_string()
+}
+
+/// Sections of an INI file (setup.cfg), with continuation lines joined by
+/// newlines
+fn parse_ini(content: &str) -> BTreeMap<String, BTreeMap<String, String>> {
+    let mut sections: BTreeMap<String, BTreeMap<String, String>> = BTreeMap::new();
+    let mut section = String::new();
+    let mut key: Option<String> = None;
+    for line in content.lines() {
+        let trimmed = line.trim();
+        if trimmed.starts_with(['#', ';']) {
+            continue;
+        }
+        if trimmed.is_empty() {
+            continue;
+        }
+        if let Some(name) = trimmed.strip_prefix('[').and_then(|s| s.strip_suffix(']')) {
+            section = name.trim().to_string();
+            key = None;
+            continue;
+        }
+        let entries = sections.entry(section.clone()).or_default();
+        if line.starts_with([' ', '\t']) {
+            if let Some(value) = key.as_ref().and_then(|k| entries.get_mut(k)) {
+                if !value.is_empty() {
+                    value.push('\n');
+                }
+                value.push_str(trimmed);
+            }
+            continue;
+        }
+        if let Some((k, v)) = trimmed.split_once(['=', ':']) {
+            let k = k.trim().replace('-', "_");
+            entries.insert(k.clone(), v.trim().to_string());
+            key = Some(k);
+        }
+    }
+    sections
+}
+
+/// Metadata in the `[metadata]` and `[options]` sections of setup.cfg
+fn parse_setup_cfg(content: &str) -> (PythonMetadata, Option<VersionSource>) {
+    let sections = parse_ini(content);
+    let get = |section: &str, key: &str| {
+        sections
+            .get(section)
+            .and_then(|s| s.get(key))
+            .map(|v| v.trim().to_string())
+            .filter(|v| !v.is_empty())
+    };
+    let lines = |section: &str, key: &str| -> Vec<String> {
+        get(section, key)
+            .map(|v| {
+                v.lines()
+                    .map(str::trim)
+                    .filter(|l| !l.is_empty() && !l.starts_with('#'))
+                    .map(str::to_string)
+                    .collect()
+            })
+            .unwrap_or_default()
+    };
+
+    let version = get("metadata", "version");
+    let version_source = version.as_deref().and_then(|v| {
+        if let Some(attr) = v.strip_prefix("attr:") {
+            Some(VersionSource::Attr(attr.trim().to_string()))
+        } else {
+            v.strip_prefix("file:")
+                .map(|file| VersionSource::File(file.trim().to_string()))
+        }
+    });
+    let metadata = PythonMetadata {
+        name: get("metadata", "name"),
+        version: version.filter(|_| version_source.is_none()),
+        summary: get("metadata", "description"),
+        license: get("metadata", "license"),
+        urls: get("metadata", "url").into_iter().collect(),
+        requires_python: get("options", "python_requires"),
+        dependencies: lines("options", "install_requires"),
+        classifiers: lines("metadata", "classifiers"),
+    };
+    (metadata, version_source)
+}
+
+/// Metadata in core metadata (`PKG-INFO`/`METADATA`) headers, which repeat
+/// for multi-valued fields
+fn parse_core_metadata(content: &str) -> PythonMetadata {
+    let mut metadata = PythonMetadata::default();
+    let mut license_expression = None;
+    for line in content.lines().take_while(|line| !line.trim().is_empty()) {
+        let Some((field, value)) = line.split_once(':') else {
+            continue;
+        };
+        let value = value.trim();
+        if value.is_empty() || value == "UNKNOWN" {
+            continue;
+        }
+        match field.to_ascii_lowercase().as_str() {
+            "name" => metadata.name = Some(value.to_string()),
+            "version" => metadata.version = Some(value.to_string()),
+            "summary" => metadata.summary = Some(value.to_string()),
+            "license" => metadata.license = Some(value.to_string()),
+            "license-expression" => license_expression = Some(value.to_string()),
+            "home-page" => metadata.urls.push(value.to_string()),
+            "project-url" => metadata.urls.push(
+                value
+                    .split_once(',')
+                    .map_or(value, |(_, url)| url.trim())
+                    .to_string(),
+            ),
+            "requires-python" => metadata.requires_python = Some(value.to_string()),
+            "requires-dist" => {
+                let markers = value.split_once(';').map_or("", |(_, m)| m);
+                if !markers.contains("extra") {
+                    metadata.dependencies.push(value.to_string());
+                }
+            }
+            "classifier" => metadata.classifiers.push(value.to_string()),
+            _ => {}
+        }
+    }
+    metadata.license = license_expression.or(metadata.license);
+    metadata
+}
+
+/// Name and version passed as string literals to `setup()`
+fn parse_setup_py(content: &str) -> PythonMetadata {
+    let literal = |key: &str| {
+        regex::Regex::new(&format!(r#"\b{key}\s*=\s*["']([^"']+)["']"#))
+            .expect("valid regex")
+            .captures(content)
+            .map(|caps| caps[1].to_string())
+    };
+    PythonMetadata {
+        name: literal("name"),
+        version: literal("version"),
+        ..PythonMetadata::default()
+    }
+}
+
+/// Value of a version attribute: `pkg.module.__version__` (setuptools) or
+/// `path/to/file.py:__version__`
+fn attribute_version(root: &Path, attr: &str) -> Option<String> {
+    if let Some((file, variable)) = attr.split_once(':') {
+        return assigned_string(&std::fs::read_to_string(root.join(file)).ok()?, variable);
+    }
+    let (module, variable) = attr.rsplit_once('.')?;
+    let relative = module.replace('.', "/");
+    [&relative, &format!("src/{relative}")]
+        .iter()
+        .flat_map(|base| [format!("{base}.py"), format!("{base}/__init__.py")])
+        .find_map(|file| std::fs::read_to_string(root.join(file)).ok())
+        .and_then(|content| assigned_string(&content, variable))
+}
+
+/// `__version__` of an import package, from its `__init__.py` or
+/// `_version.py` (as written by setuptools-scm and versioneer-like tools)
+fn package_version(root: &Path, package: &str) -> Option<String> {
+    [root.join(package), root.join("src").join(package)]
+        .iter()
+        .flat_map(|dir| [dir.join("__init__.py"), dir.join("_version.py")])
+        .filter_map(|file| std::fs::read_to_string(file).ok())
+        .find_map(|content| {
+            assigned_string(&content, "__version__")
+                .or_else(|| assigned_string(&content, "version"))
+        })
+}
+
+/// String literal assigned to a top-level `variable` (`__version__ = "1.0"`,
+/// `__version__: str = "1.0"`, `__version__ = version = "1.0"`)
+fn assigned_string(content: &str, variable: &str) -> Option<String> {
+    let pattern = format!(
+        r#"(?m)^{}\s*(?::\s*\w+\s*)?=\s*(?:\w+\s*=\s*)*["']([^"']+)["']"#,
+        regex::escape(variable)
+    );
+    regex::Regex::new(&pattern)
+        .expect("valid regex")
+        .captures(content)
+        .map(|caps| caps[1].to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pyproject_ignores_other_tables() {
+        let dir = tempfile::TempDir::new().unwrap();
+        std::fs::write(
+            dir.path().join("pyproject.toml"),
+            r#"
+[build-system]
+requires = ["setuptools"]
+
+[tool.black]
+name = "not-the-project"
+version = "0.0.0"
+
+[project]
+name = "widgets"
+dynamic = ["version"]
+requires-python = ">=3.9"
+dependencies = ["attrs>=22", "tomli; python_version < '3.11'"]
+classifiers = ["Programming Language :: Python :: 3"]
+license = { text = "MIT" }
+
+[project.optional-dependencies]
+docs = ["sphinx"]
+
+[tool.setuptools.dynamic]
+version = { attr = "widgets.__version__" }
+"#,
+        )
+        .unwrap();
+        std::fs::create_dir_all(dir.path().join("src/widgets")).unwrap();
+        std::fs::write(
+            dir.path().join("src/widgets/__init__.py"),
+            "\"\"\"Widgets.\"\"\"\n__version__: str = \"2.4.1\"\n",
+        )
+        .unwrap();
+
+        let metadata = read(dir.path());
+        assert_eq!(metadata.name.as_deref(), Some("widgets"));
+        assert_eq!(metadata.version.as_deref(), Some("2.4.1"));
+        assert_eq!(metadata.requires_python.as_deref(), Some(">=3.9"));
+        assert_eq!(
+            metadata.dependencies,
+            ["attrs>=22", "tomli; python_version < '3.11'"]
+        );
+        assert_eq!(metadata.license.as_deref(), Some("MIT"));
+        assert_eq!(metadata.classifiers.len(), 1);
+    }
+
+    #[test]
+    fn test_fallbacks_to_setup_cfg_and_pkg_info() {
+        let dir = tempfile::TempDir::new().unwrap();
+        // A build-system-only pyproject.toml declares no metadata
+        std::fs::write(
+            dir.path().join("pyproject.toml"),
+            "[build-system]\nrequires = [\"setuptools\", \"setuptools-scm\"]\n",
+        )
+        .unwrap();
+        std::fs::write(
+            dir.path().join("setup.cfg"),
+            "[metadata]\nname = gadgets\nclassifiers =\n    License :: OSI Approved\n    Typing :: Typed\n\n[options]\npython_requires = >=3.8\ninstall_requires =\n    requests\n    click>=8\n",
+        )
+        .unwrap();
+        std::fs::write(
+            dir.path().join("PKG-INFO"),
+            "Metadata-Version: 2.1\nName: gadgets\nVersion: 0.9.0\nSummary: Gadgets\nRequires-Dist: rich; extra == \"cli\"\n\nRequires-Dist: not-a-header\n",
+        )
+        .unwrap();
+
+        let metadata = read(dir.path());
+        assert_eq!(metadata.version.as_deref(), Some("0.9.0"));
+        assert_eq!(metadata.summary.as_deref(), Some("Gadgets"));
+        assert_eq!(metadata.requires_python.as_deref(), Some(">=3.8"));
+        assert_eq!(metadata.dependencies, ["requests", "click>=8"]);
+        assert_eq!(
+            metadata.classifiers,
+            ["License :: OSI Approved", "Typing :: Typed"]
+        );
+    }
+
+    #[test]
+    fn test_dynamic_version_from_version_file() {
+        let dir = tempfile::TempDir::new().unwrap();
+        std::fs::write(
+            dir.path().join("pyproject.toml"),
+            "[project]\nname = \"scm-pkg\"\ndynamic = [\"version\"]\n",
+        )
+        .unwrap();
+        std::fs::create_dir_all(dir.path().join("scm_pkg")).unwrap();
+        std::fs::write(
+            dir.path().join("scm_pkg/_version.py"),
+            "# file generated by setuptools-scm\n__version__ = version = '1.3.0.post2'\n",
+        )
+        .unwrap();
+
+        assert_eq!(read(dir.path()).version.as_deref(), Some("1.3.0.post2"));
+    }
+
+    #[test]
+    fn test_poetry_tables() {
+        let (metadata, _) = parse_pyproject(
+            r#"
+[tool.poetry]
+name = "legacy"
+version = "1.0.0"
+
+[tool.poetry.dependencies]
+python = "^3.8"
+httpx = "^0.27"
+pendulum = { version = "~2.1", optional = true }
+"#,
+        )
+        .unwrap();
+        assert_eq!(metadata.name.as_deref(), Some("legacy"));
+        assert_eq!(metadata.requires_python.as_deref(), Some(">=3.8,<4.0"));
+        assert_eq!(metadata.dependencies, ["httpx>=0.27,<0.28"]);
+    }
+}
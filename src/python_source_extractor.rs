@@ -2,7 +2,8 @@
 //!
 //! Parses Python package source directly from downloaded tarballs without requiring installation.
 
-use crate::fetch::PackageInfo;
+use crate::fetch::{Origin, PackageInfo};
+use crate::python_metadata;
 use crate::schema::{ClassRecord, FunctionRecord, PackageRecord, Record, SCHEMA_VERSION};
 use crate::ExtractOptions;
 use anyhow::{Context, Result};
@@ -12,14 +13,12 @@ use std::process::Command;
 
 /// Extract records from a Python package source directory
 pub fn extract_from_source(pkg: &dyn PackageInfo, options: &ExtractOptions) -> Result<Vec<Record>> {
-    // Use Python AST to parse the source
-    let py_script = generate_source_parser(
-        &pkg.source_path().to_string_lossy(),
-        options.include_internal,
-    );
-
+    // Use Python AST to parse the source; the path is passed as an argument
+    // so that no directory name can be interpreted as code
     let output = Command::new("python3")
-        .args(["-c", &py_script])
+        .args(["-c", SOURCE_PARSER])
+        .arg(pkg.source_path())
+        .arg(if options.include_internal { "1" } else { "0" })
         .output()
         .context("Failed to execute python3 for source parsing")?;
 
@@ -45,17 +44,35 @@ pub fn extract_from_source(pkg: &dyn PackageInfo, options: &ExtractOptions) -> R
     let mut records = Vec::new();
 
     // Package record
+    let metadata = python_metadata::read(pkg.source_path());
     let pkg_record = PackageRecord {
         schema_version: SCHEMA_VERSION.to_string(),
         name: pkg.name().to_string(),
         version: pkg
             .version()
-            .map(|v| v.to_string())
-            .unwrap_or_else(|| parsed.version.unwrap_or_else(|| "unknown".to_string())),
+            .map(str::to_string)
+            .or(metadata.version)
+            .unwrap_or_else(|| "unknown".to_string()),
         language: "Python".to_string(),
-        description: parsed.description,
+        artifact: pkg.origin().and_then(|o| o.artifact.clone()),
+        commit: pkg.origin().and_then(|o| o.commit.clone()),
+        bioconductor_release: None,
+        description: metadata.summary.or(parsed.description),
+        license: metadata.license,
+        url: metadata.urls,
+        bug_reports: None,
+        depends: Vec::new(),
+        imports: Vec::new(),
+        suggests: Vec::new(),
+        system_requirements: None,
+        encoding: None,
+        namespace_imports: BTreeMap::new(),
+        requires_python: metadata.requires_python,
+        dependencies: metadata.dependencies,
+        classifiers: metadata.classifiers,
         llm_hints: Vec::new(),
         common_arguments: BTreeMap::new(),
+        provenance: pkg.origin().map(Origin::provenance),
     };
     records.push(Record::Package(pkg_record));
 
@@ -74,6 +91,7 @@ pub fn extract_from_source(pkg: &dyn PackageInfo, options: &ExtractOptions) -> R
 
         let func_record = FunctionRecord {
             name: func.name,
+            package: None,
             exported: true,
             signature: func.signature,
             purpose: func.docstring,
@@ -100,6 +118,7 @@ pub fn extract_from_source(pkg: &dyn PackageInfo, options: &ExtractOptions) -> R
 
             let class_record = ClassRecord {
                 name: cls.name,
+                package: None,
                 constructed_by: Vec::new(),
                 methods,
             };
@@ -112,7 +131,6 @@ pub fn extract_from_source(pkg: &dyn PackageInfo, options: &ExtractOptions) -> R
 
 #[derive(Debug, Deserialize)]
 struct PySourceInfo {
-    version: Option<String>,
     description: Option<String>,
     functions: Vec<PyFuncInfo>,
     #[serde(default)]
@@ -147,20 +165,21 @@ struct PyClassInfo {
     methods: Vec<PyFuncInfo>,
 }
 
-fn generate_source_parser(source_path: &str, include_internal: bool) -> String {
-    format!(
-        r#"
+/// Python program that parses a package source tree.
+///
+/// Invoked as `python3 -c SOURCE_PARSER <source_path> <include_internal: 0|1>`.
+const SOURCE_PARSER: &str = r#"
 import ast
 import os
 import json
 import re
+import sys
 
 def parse_package_source(source_path, include_internal=False):
     """Parse Python source files using AST."""
     
     functions = []
     classes = []
-    version = None
     description = None
     
     # Find Python files
@@ -171,23 +190,9 @@ def parse_package_source(source_path, include_internal=False):
         for f in files:
             if f.endswith('.py'):
                 py_files.append(os.path.join(root, f))
-    
-    # Try to get version from setup.py or pyproject.toml
-    setup_py = os.path.join(source_path, 'setup.py')
-    if os.path.exists(setup_py):
-        with open(setup_py) as f:
-            content = f.read()
-            m = re.search(r"version\s*=\s*['\"]([^'\"]+)['\"]", content)
-            if m:
-                version = m.group(1)
-    
-    pyproject = os.path.join(source_path, 'pyproject.toml')
-    if os.path.exists(pyproject):
-        with open(pyproject) as f:
-            content = f.read()
-            m = re.search(r'version\s*=\s*["\']([^"\']+)["\']', content)
-            if m:
-                version = m.group(1)
+            # Stubs stand in for modules shipped only as compiled extensions
+            elif f.endswith('.pyi') and f[:-1] not in files:
+                py_files.append(os.path.join(root, f))
     
     # Parse each Python file
     for py_file in py_files:
@@ -219,12 +224,11 @@ def parse_package_source(source_path, include_internal=False):
         except Exception as e:
             pass  # Skip files that can't be parsed
     
-    return {{
-        'version': version,
+    return {
         'description': description,
         'functions': functions,
         'classes': classes,
-    }}
+    }
 
 def extract_function(node):
     """Extract function info from AST node."""
@@ -237,7 +241,7 @@ def extract_function(node):
     # Regular args
     defaults_offset = len(args.args) - len(args.defaults)
     for i, arg in enumerate(args.args):
-        param = {{'name': arg.arg}}
+        param = {'name': arg.arg}
         if arg.annotation:
             param['annotation'] = sanitize(ast.unparse(arg.annotation))
         if i >= defaults_offset:
@@ -247,11 +251,11 @@ def extract_function(node):
     
     # *args
     if args.vararg:
-        params.append({{'name': '*' + args.vararg.arg}})
+        params.append({'name': '*' + args.vararg.arg})
     
     # Keyword-only args
     for i, arg in enumerate(args.kwonlyargs):
-        param = {{'name': arg.arg}}
+        param = {'name': arg.arg}
         if arg.annotation:
             param['annotation'] = sanitize(ast.unparse(arg.annotation))
         if args.kw_defaults[i]:
@@ -260,7 +264,7 @@ def extract_function(node):
     
     # **kwargs
     if args.kwarg:
-        params.append({{'name': '**' + args.kwarg.arg}})
+        params.append({'name': '**' + args.kwarg.arg})
     
     # Build signature string
     param_strs = []
@@ -272,7 +276,7 @@ def extract_function(node):
             s += ' = ' + p['default']
         param_strs.append(s)
     
-    signature = f"{{name}}({{', '.join(param_strs)}})"
+    signature = f"{name}({', '.join(param_strs)})"
     
     # Return annotation
     return_annotation = None
@@ -285,13 +289,13 @@ def extract_function(node):
     if ds:
         docstring = sanitize(ds.split('\n')[0])
     
-    return {{
+    return {
         'name': name,
         'signature': signature,
         'docstring': docstring,
         'parameters': params,
         'return_annotation': return_annotation,
-    }}
+    }
 
 def extract_class(node, include_internal):
     """Extract class info from AST node."""
@@ -302,10 +306,10 @@ def extract_class(node, include_internal):
                 continue
             methods.append(extract_function(item))
     
-    return {{
+    return {
         'name': node.name,
         'methods': methods,
-    }}
+    }
 
 def sanitize(s):
     """Remove control characters and normalize whitespace."""
@@ -314,12 +318,34 @@ def sanitize(s):
     s = re.sub(r'[\x00-\x1f\x7f-\x9f]', ' ', str(s))
     return ' '.join(s.split()).strip()
 
-result = parse_package_source("{source_path}", {include_internal})
+# Inputs arrive as arguments, never as code
+result = parse_package_source(sys.argv[1], sys.argv[2] == "1")
 print("<<<PKGCTX_JSON_START>>>", end="")
 print(json.dumps(result), end="")
 print("<<<PKGCTX_JSON_END>>>")
-"#,
-        source_path = source_path.replace('\\', "\\\\").replace('"', "\\\""),
-        include_internal = if include_internal { "True" } else { "False" }
-    )
+"#;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+
+    #[test]
+    fn test_source_path_is_not_interpreted_as_code() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let marker = dir.path().join("pwned");
+        let name = format!("pkg'''\"); open('{}', 'w'); (\"", marker.display());
+        let root = dir.path().join(name);
+        std::fs::create_dir_all(root.join("mypkg")).unwrap();
+        std::fs::write(root.join("mypkg/__init__.py"), "def hello(x):\n    pass\n").unwrap();
+
+        let pkg = crate::fetch::fetch_local_python_package(&root).unwrap();
+        let options = ExtractOptions::parse_from(["pkgctx"]);
+        let records = extract_from_source(&pkg, &options).unwrap();
+
+        assert!(!marker.exists());
+        assert!(records
+            .iter()
+            .any(|r| matches!(r, Record::Function(f) if f.name == "hello")));
+    }
 }
@@ -0,0 +1,293 @@
+//! Tokenizer for R source
+//!
+//! Splits R code (package sources, NAMESPACE files) into tokens with their
+//! byte offsets, so that callers can reason about calls and assignments
+//! without being fooled by brackets in strings or comments, and can still
+//! copy the original text of an expression. Comments are dropped; line
+//! breaks are kept as tokens because they end R expressions.
+
+/// Kind of an R token
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Token {
+    /// A name, either syntactic (`mutate`, `.data`) or backquoted
+    /// (`` `names<-` ``, stored without the backquotes)
+    Symbol(String),
+    /// A string literal, with escape sequences resolved
+    Str(String),
+    /// A numeric literal, as written (`1L`, `0x1F`, `1e-3`)
+    Number(String),
+    /// An operator (`<-`, `::`, `%in%`, `\` for lambdas, ...)
+    Op(String),
+    /// One of `(`, `)`, `{`, `}`, `[`, `]`, `,` and `;`
+    Punct(char),
+    /// A line break outside of a string
+    Newline,
+}
+
+/// A token and the byte range of its text in the source
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Spanned {
+    pub token: Token,
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Spanned {
+    /// Whether this is the punctuation `c`
+    pub fn is_punct(&self, c: char) -> bool {
+        self.token == Token::Punct(c)
+    }
+
+    /// The name of a symbol token
+    pub fn symbol(&self) -> Option<&str> {
+        match &self.token {
+            Token::Symbol(name) => Some(name),
+            _ => None,
+        }
+    }
+}
+
+/// Operators, longest first so that `<<-` wins over `<-` and `<`
+const OPERATORS: &[&str] = &[
+    "<<-", "->>", ":::", "<-", "->", "<=", ">=", "==", "!=", "&&", "||", "::", "|>", ":=", "=",
+    "<", ">", "!", "&", "|", "+", "-", "*", "/", "^", "~", "?", ":", "$", "@", "\\",
+];
+
+/// Split R source into tokens.
+///
+/// Unterminated strings and stray characters do not fail: the rest of the
+/// input becomes a string, and unknown characters are skipped, so that a
+/// file R would reject still yields what can be recognized.
+pub fn tokenize(source: &str) -> Vec<Spanned> {
+    let bytes = source.as_bytes();
+    let mut tokens = Vec::new();
+    let mut pos = 0;
+
+    while pos < bytes.len() {
+        let start = pos;
+        let c = source[pos..]
+            .chars()
+            .next()
+            .expect("position is a char boundary");
+        let token = match c {
+            '\n' => {
+                pos += 1;
+                Token::Newline
+            }
+            c if c.is_whitespace() => {
+                pos += c.len_utf8();
+                continue;
+            }
+            '#' => {
+                pos = source[pos..].find('\n').map_or(bytes.len(), |i| pos + i);
+                continue;
+            }
+            '(' | ')' | '{' | '}' | '[' | ']' | ',' | ';' => {
+                pos += 1;
+                Token::Punct(c)
+            }
+            'r' | 'R' if matches!(bytes.get(pos + 1), Some(b'"' | b'\'')) => {
+                let (value, end) = raw_string(source, pos + 1);
+                pos = end;
+                Token::Str(value)
+            }
+            '"' | '\'' => {
+                let (value, end) = quoted(source, pos, c);
+                pos = end;
+                Token::Str(value)
+            }
+            '`' => {
+                let (value, end) = quoted(source, pos, '`');
+                pos = end;
+                Token::Symbol(value)
+            }
+            '%' => {
+                // `%op%` operators, which cannot span lines
+                match source[pos + 1..].find(['%', '\n']) {
+                    Some(i) if bytes[pos + 1 + i] == b'%' => {
+                        pos += i + 2;
+                        Token::Op(source[start..pos].to_string())
+                    }
+                    _ => {
+                        pos += 1;
+                        continue;
+                    }
+                }
+            }
+            c if c.is_ascii_digit()
+                || (c == '.' && bytes.get(pos + 1).is_some_and(u8::is_ascii_digit)) =>
+            {
+                pos = number_end(bytes, pos);
+                Token::Number(source[start..pos].to_string())
+            }
+            c if c.is_alphabetic() || c == '.' => {
+                pos += source[pos..]
+                    .find(|c: char| !(c.is_alphanumeric() || c == '.' || c == '_'))
+                    .unwrap_or(bytes.len() - pos);
+                Token::Symbol(source[start..pos].to_string())
+            }
+            _ => match OPERATORS.iter().find(|op| source[pos..].starts_with(*op)) {
+                Some(op) => {
+                    pos += op.len();
+                    Token::Op((*op).to_string())
+                }
+                None => {
+                    pos += c.len_utf8();
+                    continue;
+                }
+            },
+        };
+        tokens.push(Spanned {
+            token,
+            start,
+            end: pos,
+        });
+    }
+
+    tokens
+}
+
+/// Read a string or backquoted name starting at the quote at `start`;
+/// returns its unescaped value and the offset after the closing quote
+fn quoted(source: &str, start: usize, quote: char) -> (String, usize) {
+    let mut value = String::new();
+    let mut chars = source[start + 1..].char_indices();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            c if c == quote => return (value, start + 1 + i + 1),
+            '\\' => match chars.next() {
+                Some((_, 'n')) => value.push('\n'),
+                Some((_, 't')) => value.push('\t'),
+                Some((_, 'r')) => value.push('\r'),
+                Some((_, '0')) => value.push('\0'),
+                Some((_, other)) => value.push(other),
+                None => break,
+            },
+            c => value.push(c),
+        }
+    }
+    (value, source.len())
+}
+
+/// Read a raw string (`r"(...)"`, `R'[...]'`, `r"---{...}---"`) whose quote
+/// is at `quote_pos`
+fn raw_string(source: &str, quote_pos: usize) -> (String, usize) {
+    let quote = &source[quote_pos..quote_pos + 1];
+    let rest = &source[quote_pos + 1..];
+    let dashes = rest.len() - rest.trim_start_matches('-').len();
+    let close = match rest[dashes..].chars().next() {
+        Some('(') => ')',
+        Some('[') => ']',
+        Some('{') => '}',
+        // Not a raw string after all: read it as an ordinary one
+        _ => return quoted(source, quote_pos, quote.chars().next().unwrap_or('"')),
+    };
+    let body_start = quote_pos + 1 + dashes + 1;
+    let terminator = format!("{close}{}{quote}", "-".repeat(dashes));
+    match source[body_start..].find(&terminator) {
+        Some(i) => (
+            source[body_start..body_start + i].to_string(),
+            body_start + i + terminator.len(),
+        ),
+        None => (source[body_start..].to_string(), source.len()),
+    }
+}
+
+/// Offset after the numeric literal starting at `start`
+fn number_end(bytes: &[u8], start: usize) -> usize {
+    let mut pos = start;
+    if bytes[pos] == b'0' && matches!(bytes.get(pos + 1), Some(b'x' | b'X')) {
+        pos += 2;
+        while pos < bytes.len() && bytes[pos].is_ascii_hexdigit() {
+            pos += 1;
+        }
+    } else {
+        while pos < bytes.len() && (bytes[pos].is_ascii_digit() || bytes[pos] == b'.') {
+            pos += 1;
+        }
+        if matches!(bytes.get(pos), Some(b'e' | b'E')) {
+            let mut exponent = pos + 1;
+            if matches!(bytes.get(exponent), Some(b'+' | b'-')) {
+                exponent += 1;
+            }
+            if bytes.get(exponent).is_some_and(u8::is_ascii_digit) {
+                pos = exponent;
+                while pos < bytes.len() && bytes[pos].is_ascii_digit() {
+                    pos += 1;
+                }
+            }
+        }
+    }
+    if matches!(bytes.get(pos), Some(b'L' | b'i')) {
+        pos += 1;
+    }
+    pos
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tokens(source: &str) -> Vec<Token> {
+        tokenize(source).into_iter().map(|t| t.token).collect()
+    }
+
+    #[test]
+    fn test_tokenize_names_strings_and_operators() {
+        use Token::*;
+        let sym = |s: &str| Symbol(s.to_string());
+        let op = |s: &str| Op(s.to_string());
+
+        assert_eq!(
+            tokens("`names<-` <- \\(x, value = \"a)#\") x %||% .5e-1L # (comment\n"),
+            [
+                sym("names<-"),
+                op("<-"),
+                op("\\"),
+                Punct('('),
+                sym("x"),
+                Punct(','),
+                sym("value"),
+                op("="),
+                Str("a)#".to_string()),
+                Punct(')'),
+                sym("x"),
+                op("%||%"),
+                Number(".5e-1L".to_string()),
+                Newline,
+            ]
+        );
+        assert_eq!(
+            tokens("x[[1]] <<- r\"-(a \"quoted\" )\" )-\"; y->>z"),
+            [
+                sym("x"),
+                Punct('['),
+                Punct('['),
+                Number("1".to_string()),
+                Punct(']'),
+                Punct(']'),
+                op("<<-"),
+                Str("a \"quoted\" )\" ".to_string()),
+                Punct(';'),
+                sym("y"),
+                op("->>"),
+                sym("z"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_spans_cover_the_source_text() {
+        let source = "f <- function(a = c(1,\n  2)) 'x\\'y'";
+        let spanned = tokenize(source);
+        let texts: Vec<&str> = spanned.iter().map(|t| &source[t.start..t.end]).collect();
+        assert_eq!(
+            texts,
+            [
+                "f", "<-", "function", "(", "a", "=", "c", "(", "1", ",", "\n", "2", ")", ")",
+                "'x\\'y'"
+            ]
+        );
+        assert_eq!(spanned.last().unwrap().token, Token::Str("x'y".to_string()));
+    }
+}
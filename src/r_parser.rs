@@ -0,0 +1,725 @@
+//! Expression parser for R source
+//!
+//! Builds a light syntax tree from the tokens of [`crate::r_lexer`], enough
+//! to find the closures a package assigns at top level (`f <- function(x)`,
+//! `` `names<-` = \(x, value) ``, `assign("f", function() ...)`) and to walk
+//! their bodies. Every node keeps the byte range of its source text, so that
+//! default arguments are reported exactly as written.
+//!
+//! The parser is lenient: a token it cannot place is skipped, so one syntax
+//! error does not hide the rest of a file.
+
+use crate::r_lexer::{self, Spanned, Token};
+
+/// A node of the syntax tree and the byte range of its source text
+#[derive(Debug, Clone)]
+pub struct Expr {
+    pub kind: ExprKind,
+    pub start: usize,
+    pub end: usize,
+}
+
+/// Kinds of syntax tree nodes
+#[derive(Debug, Clone)]
+pub enum ExprKind {
+    /// A name (syntactic or backquoted), including `NULL`, `TRUE`, ...
+    Symbol(String),
+    /// A string literal
+    Str(String),
+    /// A numeric literal
+    Number,
+    /// A call: `f(x, y = 2)`
+    Call { function: Box<Expr>, args: Vec<Arg> },
+    /// Indexing: `x[i]`, `x[[i]]`
+    Index { object: Box<Expr>, args: Vec<Arg> },
+    /// A closure: `function(x) body` or `\(x) body`
+    Function { params: Vec<Param>, body: Box<Expr> },
+    /// A binary operation, including assignments (`<-`, `=`, `->`, ...)
+    Binary {
+        op: String,
+        lhs: Box<Expr>,
+        rhs: Box<Expr>,
+    },
+    /// A unary operation (`-x`, `!x`, `~x`)
+    Unary(Box<Expr>),
+    /// `{ ... }`
+    Block(Vec<Expr>),
+    /// `( ... )`
+    Paren(Box<Expr>),
+    /// `if`, `for`, `while` and `repeat`, with their parts in order
+    Control(Vec<Expr>),
+    /// Nothing could be parsed here
+    Missing,
+}
+
+/// An argument of a call or index
+#[derive(Debug, Clone)]
+pub struct Arg {
+    pub name: Option<String>,
+    /// `None` for an empty argument (`x[, 1]`)
+    pub value: Option<Expr>,
+}
+
+/// A formal parameter of a closure
+#[derive(Debug, Clone)]
+pub struct Param {
+    pub name: String,
+    pub default: Option<Expr>,
+}
+
+impl Expr {
+    /// Visit this node and every node below it
+    pub fn walk(&self, visit: &mut dyn FnMut(&Expr)) {
+        visit(self);
+        match &self.kind {
+            ExprKind::Call { function, args } => {
+                function.walk(visit);
+                args.iter()
+                    .filter_map(|a| a.value.as_ref())
+                    .for_each(|v| v.walk(visit));
+            }
+            ExprKind::Index { object, args } => {
+                object.walk(visit);
+                args.iter()
+                    .filter_map(|a| a.value.as_ref())
+                    .for_each(|v| v.walk(visit));
+            }
+            ExprKind::Function { params, body } => {
+                params
+                    .iter()
+                    .filter_map(|p| p.default.as_ref())
+                    .for_each(|d| d.walk(visit));
+                body.walk(visit);
+            }
+            ExprKind::Binary { lhs, rhs, .. } => {
+                lhs.walk(visit);
+                rhs.walk(visit);
+            }
+            ExprKind::Unary(operand) | ExprKind::Paren(operand) => operand.walk(visit),
+            ExprKind::Block(exprs) | ExprKind::Control(exprs) => {
+                exprs.iter().for_each(|e| e.walk(visit));
+            }
+            ExprKind::Symbol(_) | ExprKind::Str(_) | ExprKind::Number | ExprKind::Missing => {}
+        }
+    }
+
+    /// Name of the function called by this node (`f` for `f(x)` and
+    /// `pkg::f(x)`)
+    pub fn call_name(&self) -> Option<&str> {
+        let ExprKind::Call { function, .. } = &self.kind else {
+            return None;
+        };
+        match &function.kind {
+            ExprKind::Symbol(name) => Some(name),
+            ExprKind::Binary { op, rhs, .. } if op == "::" || op == ":::" => match &rhs.kind {
+                ExprKind::Symbol(name) => Some(name),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    /// Name given by a symbol or string node
+    fn name(&self) -> Option<&str> {
+        match &self.kind {
+            ExprKind::Symbol(name) | ExprKind::Str(name) => Some(name),
+            _ => None,
+        }
+    }
+}
+
+/// A closure assigned to a name at the top level of a file
+#[derive(Debug, Clone)]
+pub struct Closure<'a> {
+    pub name: String,
+    pub params: &'a [Param],
+    pub body: &'a Expr,
+}
+
+impl Closure<'_> {
+    /// Parameters of the closure, with default expressions as written in
+    /// `source`
+    pub fn parameters(&self, source: &str) -> Vec<(String, Option<String>)> {
+        self.params
+            .iter()
+            .map(|p| {
+                let default = p
+                    .default
+                    .as_ref()
+                    .map(|d| source[d.start..d.end].to_string());
+                (p.name.clone(), default)
+            })
+            .collect()
+    }
+
+    /// Signature of the closure, with default expressions as written in
+    /// `source` (e.g. `` `names<-`(x, value) ``, `f(x, n = 1L)`)
+    pub fn signature(&self, source: &str) -> String {
+        format_signature(&self.name, &self.parameters(source))
+    }
+}
+
+/// Format a call signature from a function name and its parameters with
+/// their default expressions
+pub fn format_signature(name: &str, params: &[(String, Option<String>)]) -> String {
+    let params: Vec<String> = params
+        .iter()
+        .map(|(param, default)| match default {
+            Some(default) => format!("{} = {default}", quote_name(param)),
+            None => quote_name(param),
+        })
+        .collect();
+    format!("{}({})", quote_name(name), params.join(", "))
+}
+
+/// Closures assigned at the top level of parsed R code, in source order.
+///
+/// Recognizes `<-`, `<<-`, `=`, `->` and `->>` assignments (also chained,
+/// as in `f <- g <- function()`) and `assign("name", function() ...)`.
+pub fn closures(exprs: &[Expr]) -> Vec<Closure<'_>> {
+    let mut closures = Vec::new();
+    for expr in exprs {
+        let mut names = Vec::new();
+        let mut value = expr;
+        while let Some((name, assigned)) = assignment(value) {
+            names.push(name);
+            value = assigned;
+        }
+        while let ExprKind::Paren(inner) = &value.kind {
+            value = inner;
+        }
+        if let ExprKind::Function { params, body } = &value.kind {
+            closures.extend(names.into_iter().map(|name| Closure {
+                name: name.to_string(),
+                params,
+                body,
+            }));
+        }
+    }
+    closures
+}
+
+/// Name and value of an assignment
+fn assignment(expr: &Expr) -> Option<(&str, &Expr)> {
+    match &expr.kind {
+        ExprKind::Binary { op, lhs, rhs } => match op.as_str() {
+            "<-" | "<<-" | "=" => Some((lhs.name()?, rhs)),
+            "->" | "->>" => Some((rhs.name()?, lhs)),
+            _ => None,
+        },
+        ExprKind::Call { args, .. } if expr.call_name() == Some("assign") => {
+            let positional = |i: usize| args.iter().filter(|a| a.name.is_none()).nth(i);
+            let named = |n: &str| args.iter().find(|a| a.name.as_deref() == Some(n));
+            let name = named("x").or_else(|| positional(0))?.value.as_ref()?;
+            let value = named("value")
+                .or_else(|| positional(if named("x").is_some() { 0 } else { 1 }))?
+                .value
+                .as_ref()?;
+            match &name.kind {
+                ExprKind::Str(name) => Some((name, value)),
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+/// Backquote a name unless it is syntactic
+pub fn quote_name(name: &str) -> String {
+    const RESERVED: &[&str] = &[
+        "if", "else", "repeat", "while", "function", "for", "next", "break", "TRUE", "FALSE",
+        "NULL", "Inf", "NaN", "NA", "in",
+    ];
+    let mut chars = name.chars();
+    let syntactic = match chars.next() {
+        Some(c) if c.is_alphabetic() => true,
+        Some('.') => !chars.next().is_some_and(|c| c.is_ascii_digit()),
+        _ => false,
+    } && name
+        .chars()
+        .all(|c| c.is_alphanumeric() || c == '.' || c == '_')
+        && !RESERVED.contains(&name);
+    if syntactic {
+        name.to_string()
+    } else {
+        format!("`{name}`")
+    }
+}
+
+/// Parse R source into its top-level expressions
+pub fn parse(source: &str) -> Vec<Expr> {
+    let tokens = r_lexer::tokenize(source);
+    let mut parser = Parser {
+        tokens: &tokens,
+        pos: 0,
+        last_end: 0,
+        newlines_matter: vec![true],
+    };
+    parser.statements(None)
+}
+
+/// Binding powers (left, right) of binary operators; higher binds tighter
+fn binding_power(op: &str) -> Option<(u8, u8)> {
+    Some(match op {
+        "?" => (1, 2),
+        "=" => (3, 3),
+        "<-" | "<<-" | ":=" => (5, 5),
+        "->" | "->>" => (6, 7),
+        "~" => (8, 9),
+        "||" | "|" => (10, 11),
+        "&&" | "&" => (12, 13),
+        "==" | "!=" | "<" | ">" | "<=" | ">=" => (16, 17),
+        "+" | "-" => (18, 19),
+        "*" | "/" => (20, 21),
+        "|>" => (22, 23),
+        op if op.starts_with('%') => (22, 23),
+        ":" => (24, 25),
+        "^" => (28, 28),
+        "$" | "@" => (30, 31),
+        "::" | ":::" => (32, 33),
+        _ => return None,
+    })
+}
+
+/// Binding power of calls and indexing, which apply to any operand
+const POSTFIX_POWER: u8 = 29;
+/// Binding power of the operand of unary minus and plus
+const UNARY_MINUS_POWER: u8 = 26;
+/// Binding power of the operand of `!`
+const NOT_POWER: u8 = 14;
+/// Binding power of bodies (functions, `if`, loops), which take assignments
+const BODY_POWER: u8 = 3;
+
+struct Parser<'a> {
+    tokens: &'a [Spanned],
+    pos: usize,
+    /// End of the last consumed token
+    last_end: usize,
+    /// Whether line breaks end expressions in each enclosing bracket
+    /// (they do at top level and in braces, not in parentheses)
+    newlines_matter: Vec<bool>,
+}
+
+impl Parser<'_> {
+    /// Next token, skipping line breaks where they do not matter
+    fn peek(&mut self) -> Option<&Spanned> {
+        if !self.newlines_matter.last().copied().unwrap_or(true) {
+            self.skip_newlines();
+        }
+        self.tokens.get(self.pos)
+    }
+
+    fn skip_newlines(&mut self) {
+        while self
+            .tokens
+            .get(self.pos)
+            .is_some_and(|t| t.token == Token::Newline)
+        {
+            self.pos += 1;
+        }
+    }
+
+    fn next(&mut self) -> Option<&Spanned> {
+        self.peek()?;
+        let token = &self.tokens[self.pos];
+        self.pos += 1;
+        self.last_end = token.end;
+        Some(token)
+    }
+
+    /// Consume the punctuation `c` if it comes next
+    fn eat(&mut self, c: char) -> bool {
+        if self.peek().is_some_and(|t| t.is_punct(c)) {
+            self.next();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn node(&self, kind: ExprKind, start: usize) -> Expr {
+        Expr {
+            kind,
+            start,
+            end: self.last_end.max(start),
+        }
+    }
+
+    /// Expressions up to `close` (or the end of input), separated by line
+    /// breaks and semicolons
+    fn statements(&mut self, close: Option<char>) -> Vec<Expr> {
+        self.newlines_matter.push(true);
+        let mut exprs = Vec::new();
+        loop {
+            while self
+                .tokens
+                .get(self.pos)
+                .is_some_and(|t| t.token == Token::Newline || t.is_punct(';'))
+            {
+                self.pos += 1;
+            }
+            let Some(token) = self.tokens.get(self.pos) else {
+                break;
+            };
+            if close.is_some_and(|c| token.is_punct(c)) {
+                self.next();
+                break;
+            }
+            let before = self.pos;
+            let expr = self.expression(0);
+            if self.pos == before {
+                // Nothing starts here: skip the token
+                self.next();
+            } else {
+                exprs.push(expr);
+            }
+        }
+        self.newlines_matter.pop();
+        exprs
+    }
+
+    /// Parse an expression whose operators bind at least as tightly as
+    /// `min_power`
+    fn expression(&mut self, min_power: u8) -> Expr {
+        let last_end = self.last_end;
+        let start = self.peek().map_or(last_end, |t| t.start);
+        let mut lhs = self.prefix();
+
+        while let Some(token) = self.peek() {
+            match &token.token {
+                Token::Punct('(') if POSTFIX_POWER >= min_power => {
+                    self.next();
+                    let args = self.arguments(')');
+                    lhs = self.node(
+                        ExprKind::Call {
+                            function: Box::new(lhs),
+                            args,
+                        },
+                        start,
+                    );
+                }
+                Token::Punct('[') if POSTFIX_POWER >= min_power => {
+                    self.next();
+                    let double = self.eat('[');
+                    let args = self.arguments(']');
+                    if double {
+                        self.eat(']');
+                    }
+                    lhs = self.node(
+                        ExprKind::Index {
+                            object: Box::new(lhs),
+                            args,
+                        },
+                        start,
+                    );
+                }
+                Token::Op(op) => {
+                    let op = op.clone();
+                    let Some((left, right)) = binding_power(&op) else {
+                        break;
+                    };
+                    if left < min_power {
+                        break;
+                    }
+                    self.next();
+                    self.skip_newlines();
+                    let rhs = self.expression(right);
+                    lhs = self.node(
+                        ExprKind::Binary {
+                            op,
+                            lhs: Box::new(lhs),
+                            rhs: Box::new(rhs),
+                        },
+                        start,
+                    );
+                }
+                _ => break,
+            }
+        }
+        lhs
+    }
+
+    /// Parse an operand: a literal, name, bracketed expression, unary
+    /// operation, closure or control flow construct
+    fn prefix(&mut self) -> Expr {
+        let Some(token) = self.peek() else {
+            return self.node(ExprKind::Missing, self.last_end);
+        };
+        let start = token.start;
+        let token = token.token.clone();
+        match token {
+            Token::Symbol(name) => {
+                self.next();
+                match name.as_str() {
+                    "function" => self.function(start),
+                    "if" => {
+                        let mut parts = vec![self.condition()];
+                        parts.push(self.body());
+                        // `else` may follow on a later line inside braces
+                        let resume = self.pos;
+                        self.skip_newlines();
+                        if self.tokens.get(self.pos).and_then(Spanned::symbol) == Some("else") {
+                            self.next();
+                            parts.push(self.body());
+                        } else {
+                            self.pos = resume;
+                        }
+                        self.node(ExprKind::Control(parts), start)
+                    }
+                    "for" | "while" => {
+                        let parts = vec![self.condition(), self.body()];
+                        self.node(ExprKind::Control(parts), start)
+                    }
+                    "repeat" => {
+                        let parts = vec![self.body()];
+                        self.node(ExprKind::Control(parts), start)
+                    }
+                    _ => self.node(ExprKind::Symbol(name), start),
+                }
+            }
+            Token::Str(value) => {
+                self.next();
+                self.node(ExprKind::Str(value), start)
+            }
+            Token::Number(_) => {
+                self.next();
+                self.node(ExprKind::Number, start)
+            }
+            Token::Op(op) => match op.as_str() {
+                "\\" => {
+                    self.next();
+                    self.function(start)
+                }
+                "-" | "+" | "!" | "~" | "?" => {
+                    self.next();
+                    self.skip_newlines();
+                    let power = match op.as_str() {
+                        "-" | "+" => UNARY_MINUS_POWER,
+                        "!" => NOT_POWER,
+                        _ => 9,
+                    };
+                    let operand = self.expression(power);
+                    self.node(ExprKind::Unary(Box::new(operand)), start)
+                }
+                _ => self.node(ExprKind::Missing, start),
+            },
+            Token::Punct('(') => {
+                self.next();
+                self.newlines_matter.push(false);
+                let inner = self.expression(0);
+                self.eat(')');
+                self.newlines_matter.pop();
+                self.node(ExprKind::Paren(Box::new(inner)), start)
+            }
+            Token::Punct('{') => {
+                self.next();
+                let exprs = self.statements(Some('}'));
+                self.node(ExprKind::Block(exprs), start)
+            }
+            Token::Punct(_) | Token::Newline => self.node(ExprKind::Missing, start),
+        }
+    }
+
+    /// The parameters and body of a closure, after `function` or `\`
+    fn function(&mut self, start: usize) -> Expr {
+        let mut params = Vec::new();
+        if self.eat('(') {
+            self.newlines_matter.push(false);
+            while let Some(token) = self.next() {
+                let name = match &token.token {
+                    Token::Punct(')') => break,
+                    Token::Punct(',') => continue,
+                    Token::Symbol(name) | Token::Str(name) => name.clone(),
+                    _ => continue,
+                };
+                let default = if self
+                    .peek()
+                    .is_some_and(|t| t.token == Token::Op("=".into()))
+                {
+                    self.next();
+                    let before = self.pos;
+                    let default = self.expression(BODY_POWER + 1);
+                    (self.pos > before).then_some(default)
+                } else {
+                    None
+                };
+                params.push(Param { name, default });
+            }
+            self.newlines_matter.pop();
+        }
+        let body = self.body();
+        self.node(
+            ExprKind::Function {
+                params,
+                body: Box::new(body),
+            },
+            start,
+        )
+    }
+
+    /// A parenthesized condition (`if`, `while`) or loop header (`for`)
+    fn condition(&mut self) -> Expr {
+        let last_end = self.last_end;
+        let start = self.peek().map_or(last_end, |t| t.start);
+        if !self.eat('(') {
+            return self.node(ExprKind::Missing, start);
+        }
+        self.newlines_matter.push(false);
+        let mut parts = Vec::new();
+        while self.peek().is_some_and(|t| !t.is_punct(')')) {
+            let before = self.pos;
+            parts.push(self.expression(0));
+            if self.pos == before {
+                // `in` of `for (x in xs)` and anything unexpected
+                self.next();
+            }
+        }
+        self.eat(')');
+        self.newlines_matter.pop();
+        self.node(ExprKind::Control(parts), start)
+    }
+
+    /// The body of a closure or control flow construct, which may start on
+    /// the next line
+    fn body(&mut self) -> Expr {
+        self.skip_newlines();
+        self.expression(BODY_POWER)
+    }
+
+    /// Arguments of a call or index up to `close`
+    fn arguments(&mut self, close: char) -> Vec<Arg> {
+        self.newlines_matter.push(false);
+        let mut args = Vec::new();
+        let mut pending: Option<Arg> = None;
+        while let Some(token) = self.peek().cloned() {
+            if token.is_punct(close) {
+                self.next();
+                break;
+            }
+            if token.is_punct(',') {
+                self.next();
+                args.push(pending.take().unwrap_or(Arg {
+                    name: None,
+                    value: None,
+                }));
+                continue;
+            }
+
+            // `name = value`
+            let named = match &token.token {
+                Token::Symbol(name) | Token::Str(name) => self
+                    .tokens
+                    .get(self.pos + 1..)
+                    .and_then(|rest| rest.iter().find(|t| t.token != Token::Newline))
+                    .is_some_and(|t| t.token == Token::Op("=".into()))
+                    .then(|| name.clone()),
+                _ => None,
+            };
+            let before = self.pos;
+            let arg = match named {
+                Some(name) => {
+                    self.next();
+                    self.next();
+                    let value = match self.peek() {
+                        Some(t) if t.is_punct(',') || t.is_punct(close) => None,
+                        _ => Some(self.expression(BODY_POWER + 1)),
+                    };
+                    Arg {
+                        name: Some(name),
+                        value,
+                    }
+                }
+                None => Arg {
+                    name: None,
+                    value: Some(self.expression(BODY_POWER + 1)),
+                },
+            };
+            if self.pos == before {
+                self.next();
+                continue;
+            }
+            pending = Some(arg);
+        }
+        args.extend(pending);
+        self.newlines_matter.pop();
+        args
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn signatures(source: &str) -> Vec<String> {
+        let exprs = parse(source);
+        closures(&exprs)
+            .iter()
+            .map(|c| c.signature(source))
+            .collect()
+    }
+
+    #[test]
+    fn test_finds_top_level_closures() {
+        let source = r#"
+#' Docs with a fake(definition <- function(x))
+`names<-.tbl` <- function(x, value) x
+`%||%` <- function(x, y) if (is.null(x)) y else x
+compose <- \(f, g) \(x) g(f(x))
+wrapped <-
+  function(data,
+           sep = ", ",   # a comment (with a paren
+           fn = function(x) paste(x, collapse = ")")) {
+    inner <- function(z) z
+    NULL
+  }
+(function(x) x) -> pointed
+first <- second <- function() NULL
+assign("assigned", function(a, ...) a)
+"quoted" = function(.x) .x
+not_a_function <- c(1, 2)
+if (TRUE) conditional <- function() 1
+"#;
+        assert_eq!(
+            signatures(source),
+            [
+                "`names<-.tbl`(x, value)",
+                "`%||%`(x, y)",
+                "compose(f, g)",
+                "wrapped(data, sep = \", \", fn = function(x) paste(x, collapse = \")\"))",
+                "pointed(x)",
+                "first()",
+                "second()",
+                "assigned(a, ...)",
+                "quoted(.x)",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_defaults_keep_their_whitespace() {
+        let source =
+            "f <- function(x,\n  cols = c(\n    \"a\",\n    \"b\"\n  ),\n  n=1L) {\n  x\n}\n";
+        assert_eq!(
+            signatures(source),
+            ["f(x, cols = c(\n    \"a\",\n    \"b\"\n  ), n = 1L)"]
+        );
+    }
+
+    #[test]
+    fn test_precedence_and_recovery() {
+        let exprs = parse("x <- -2^2 + y$z[[1]](a = , b) %in% w\n) ) g <- function(x) x\n");
+        let ExprKind::Binary { op, rhs, .. } = &exprs[0].kind else {
+            panic!("expected an assignment");
+        };
+        assert_eq!(op, "<-");
+        let ExprKind::Binary { op, lhs, .. } = &rhs.kind else {
+            panic!("expected an addition");
+        };
+        assert_eq!(op, "+");
+        assert!(matches!(lhs.kind, ExprKind::Unary(_)));
+
+        // The stray parentheses are skipped
+        let names: Vec<String> = closures(&exprs).into_iter().map(|c| c.name).collect();
+        assert_eq!(names, ["g"]);
+    }
+}
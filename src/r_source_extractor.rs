@@ -2,11 +2,15 @@
 //!
 //! Parses R package source directly from downloaded tarballs without requiring installation.
 
-use crate::fetch::PackageInfo;
+use crate::dcf;
+use crate::fetch::{Origin, PackageInfo};
+use crate::namespace::Namespace;
+use crate::r_parser::{self, Expr, ExprKind};
+use crate::s3;
 use crate::schema::{Example, FunctionRecord, PackageRecord, Record, SCHEMA_VERSION};
 use crate::ExtractOptions;
 use anyhow::{Context, Result};
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 use std::fs;
 use std::path::Path;
 
@@ -15,100 +19,111 @@ pub fn extract_from_source(pkg: &dyn PackageInfo, options: &ExtractOptions) -> R
     let mut records = Vec::new();
 
     // Parse DESCRIPTION for package metadata
-    let (title, description) = parse_description(pkg.source_path())?;
+    let description = dcf::read(&pkg.source_path().join("DESCRIPTION"))
+        .context("Failed to read DESCRIPTION file")?;
+
+    // Parse NAMESPACE for exports and imports, with the S3 methods the code
+    // registers when the package is loaded
+    let sources = read_r_files(
+        pkg.source_path(),
+        description.get("Collate").map(String::as_str),
+    )?;
+    let mut namespace = Namespace::read(pkg.source_path());
+    for source in &sources {
+        namespace
+            .s3_methods
+            .extend(s3::registrations(&source.exprs));
+    }
+    let field = |name: &str| description.get(name).map(|value| sanitize(value));
+    let list = |name: &str| {
+        description
+            .get(name)
+            .map(|value| dcf::split_list(value))
+            .unwrap_or_default()
+    };
 
     let pkg_record = PackageRecord {
         schema_version: SCHEMA_VERSION.to_string(),
         name: pkg.name().to_string(),
         version: pkg.version().unwrap_or("unknown").to_string(),
         language: "R".to_string(),
-        description: title.or(description),
+        artifact: pkg.origin().and_then(|o| o.artifact.clone()),
+        commit: pkg.origin().and_then(|o| o.commit.clone()),
+        bioconductor_release: pkg.origin().and_then(|o| o.bioconductor_release.clone()),
+        description: field("Title").or_else(|| field("Description")),
+        license: field("License"),
+        // URLs are separated by commas, but often only by whitespace
+        url: description
+            .get("URL")
+            .map(|value| {
+                value
+                    .split(|c: char| c == ',' || c.is_whitespace())
+                    .filter(|url| !url.is_empty())
+                    .map(str::to_string)
+                    .collect()
+            })
+            .unwrap_or_default(),
+        bug_reports: field("BugReports"),
+        depends: list("Depends"),
+        imports: list("Imports"),
+        suggests: list("Suggests"),
+        system_requirements: field("SystemRequirements"),
+        encoding: field("Encoding"),
+        namespace_imports: namespace.imports.clone(),
+        requires_python: None,
+        dependencies: Vec::new(),
+        classifiers: Vec::new(),
         llm_hints: Vec::new(),
         common_arguments: BTreeMap::new(),
+        provenance: pkg.origin().map(Origin::provenance),
     };
     records.push(Record::Package(pkg_record));
 
-    // Parse NAMESPACE for exported functions
-    let exports = parse_namespace(pkg.source_path())?;
-
     // Parse Rd files for documentation
     let rd_docs = parse_rd_files(pkg.source_path())?;
 
     // Parse R files for function signatures
-    let functions = parse_r_files(
-        pkg.source_path(),
-        &exports,
-        &rd_docs,
-        options.include_internal,
-    )?;
-
-    for func in functions {
-        records.push(Record::Function(func));
-    }
-
-    Ok(records)
-}
-
-/// Parse DESCRIPTION file for title and description
-fn parse_description(path: &Path) -> Result<(Option<String>, Option<String>)> {
-    let desc_path = path.join("DESCRIPTION");
-    let content = fs::read_to_string(&desc_path).context("Failed to read DESCRIPTION file")?;
-
-    let mut title = None;
-    let mut description = None;
-    let mut in_description = false;
-    let mut desc_lines = Vec::new();
-
-    for line in content.lines() {
-        if let Some(t) = line.strip_prefix("Title:") {
-            title = Some(sanitize(t.trim()));
-            in_description = false;
-        } else if let Some(d) = line.strip_prefix("Description:") {
-            desc_lines.push(d.trim().to_string());
-            in_description = true;
-        } else if in_description {
-            if line.starts_with(' ') || line.starts_with('\t') {
-                desc_lines.push(line.trim().to_string());
-            } else {
-                in_description = false;
-            }
-        }
-    }
-
-    if !desc_lines.is_empty() {
-        description = Some(sanitize(&desc_lines.join(" ")));
+    let mut functions = Vec::new();
+    let mut generics = Vec::new();
+    let mut first_parameters = HashMap::new();
+    for source in &sources {
+        functions.extend(extract_functions_from_r(
+            &source.content,
+            &source.exprs,
+            &namespace,
+            &rd_docs,
+            options.include_internal,
+        ));
+        generics.extend(s3::generics(&source.exprs));
+        first_parameters.extend(s3::first_parameters(&source.exprs));
     }
 
-    Ok((title, description))
-}
-
-/// Parse NAMESPACE file for exported functions
-fn parse_namespace(path: &Path) -> Result<Vec<String>> {
-    let ns_path = path.join("NAMESPACE");
-    // NAMESPACE might not exist for some packages
-    let content = fs::read_to_string(&ns_path).unwrap_or_default();
-
-    let mut exports = Vec::new();
+    // Exported functions whose definition was not found in the code
+    // (built by factories, in `local()`, or as `name <- other_fn`) are
+    // known from their documented usage
+    let defined: BTreeSet<String> = functions.iter().map(|f| f.name.clone()).collect();
+    let documented = usage_functions(&rd_docs, &namespace, &defined);
+
+    // Methods are listed under their generic
+    functions.extend(documented);
+    records.extend(s3::group(
+        functions,
+        &generics,
+        &namespace.s3_methods,
+        &first_parameters,
+    ));
 
-    for line in content.lines() {
-        let line = line.trim();
-        // export(func1, func2, ...)
-        if let Some(rest) = line.strip_prefix("export(") {
-            if let Some(inner) = rest.strip_suffix(')') {
-                for name in inner.split(',') {
-                    exports.push(name.trim().to_string());
-                }
-            }
-        }
-        // exportPattern("^[^.]") - export all non-dot functions
-        // S3method(generic, class) - export S3 method
-    }
-
-    Ok(exports)
+    Ok(records)
 }
 
 /// Parsed Rd documentation
 struct RdDoc {
+    /// Path of the file, relative to the package root
+    file: String,
+    /// Topics of the file: its `\name{}` and every `\alias{}`
+    aliases: Vec<String>,
+    /// Calls shown in `\usage{}`
+    usages: Vec<Usage>,
     title: Option<String>,
     description: Option<String>,
     arguments: BTreeMap<String, String>,
@@ -116,40 +131,74 @@ struct RdDoc {
     examples: Vec<String>,
 }
 
-/// Parse all Rd files in man/ directory
-fn parse_rd_files(path: &Path) -> Result<BTreeMap<String, RdDoc>> {
-    let mut docs = BTreeMap::new();
+/// Rd documentation of a package, indexed by topic
+#[derive(Default)]
+struct RdIndex {
+    docs: Vec<RdDoc>,
+    /// Position in `docs` of the file documenting each alias
+    topics: BTreeMap<String, usize>,
+}
+
+impl RdIndex {
+    /// Documentation of the topic `name`
+    fn get(&self, name: &str) -> Option<&RdDoc> {
+        self.topics.get(name).map(|&i| &self.docs[i])
+    }
+}
+
+/// Parse all Rd files in man/ directory.
+///
+/// Files are indexed by every alias they declare, since one file often
+/// documents several functions (`@rdname`, `reexports.Rd`); the file stem
+/// serves as a fallback topic.
+fn parse_rd_files(path: &Path) -> Result<RdIndex> {
+    let mut index = RdIndex::default();
 
     let man_path = path.join("man");
     if !man_path.exists() {
-        return Ok(docs);
+        return Ok(index);
     }
 
+    let mut files = Vec::new();
     for entry in fs::read_dir(&man_path)? {
-        let entry = entry?;
-        let file_path = entry.path();
-
+        let file_path = entry?.path();
         if file_path.extension().is_some_and(|e| e == "Rd") {
-            if let Ok(content) = fs::read_to_string(&file_path) {
-                let name = file_path
-                    .file_stem()
-                    .and_then(|s| s.to_str())
-                    .map(ToString::to_string)
-                    .unwrap_or_default();
-
-                if let Ok(doc) = parse_rd_content(&content) {
-                    docs.insert(name, doc);
-                }
-            }
+            files.push(file_path);
         }
     }
+    files.sort();
 
-    Ok(docs)
+    for file_path in files {
+        let Ok(content) = fs::read_to_string(&file_path) else {
+            continue;
+        };
+        let Ok(mut doc) = parse_rd_content(&content) else {
+            continue;
+        };
+        if let Some(file_name) = file_path.file_name().and_then(|s| s.to_str()) {
+            doc.file = format!("man/{file_name}");
+        }
+        if let Some(stem) = file_path.file_stem().and_then(|s| s.to_str()) {
+            doc.aliases.push(stem.to_string());
+        }
+
+        // The first file declaring a topic documents it
+        let position = index.docs.len();
+        for alias in &doc.aliases {
+            index.topics.entry(alias.clone()).or_insert(position);
+        }
+        index.docs.push(doc);
+    }
+
+    Ok(index)
 }
 
 /// Parse Rd file content
 fn parse_rd_content(content: &str) -> Result<RdDoc> {
     let mut doc = RdDoc {
+        file: String::new(),
+        aliases: parse_aliases(content),
+        usages: Vec::new(),
         title: None,
         description: None,
         arguments: BTreeMap::new(),
@@ -176,10 +225,191 @@ fn parse_rd_content(content: &str) -> Result<RdDoc> {
     if let Some(examples) = sections.get("examples") {
         doc.examples = parse_example_blocks(examples);
     }
+    if let Some(usage) = sections.get("usage") {
+        doc.usages = parse_usage_section(usage);
+    }
 
     Ok(doc)
 }
 
+/// Topics declared by `\name{}` and `\alias{}` lines, with Rd escapes
+/// (`\%`, `\{`) removed
+fn parse_aliases(content: &str) -> Vec<String> {
+    let mut aliases: Vec<String> = Vec::new();
+    for line in content.lines() {
+        let line = line.trim();
+        let Some(rest) = line
+            .strip_prefix("\\alias{")
+            .or_else(|| line.strip_prefix("\\name{"))
+        else {
+            continue;
+        };
+        let Some(end) = rest.rfind('}') else {
+            continue;
+        };
+        let mut alias = String::new();
+        let mut chars = rest[..end].chars();
+        while let Some(c) = chars.next() {
+            match c {
+                '\\' => alias.extend(chars.next()),
+                c => alias.push(c),
+            }
+        }
+        let alias = alias.trim().to_string();
+        if !alias.is_empty() && !aliases.contains(&alias) {
+            aliases.push(alias);
+        }
+    }
+    aliases
+}
+
+/// A call shown in an Rd `\usage{}` section
+struct Usage {
+    /// Function name: `print.tbl` for `\method{print}{tbl}(x)`, `names<-`
+    /// for `names(x) <- value`, `%>%` for `lhs \%>\% rhs`
+    name: String,
+    /// Parameters with their default expressions, as written
+    params: Vec<(String, Option<String>)>,
+    /// Whether this shows an S4 method (`\S4method{show}{Tbl}(object)`),
+    /// named after its generic but with the arguments of one class
+    s4_method: bool,
+}
+
+/// Marks the generic name of an S4 method usage in the rewritten R code
+const S4_METHOD_MARK: char = '\u{1}';
+
+impl Usage {
+    fn signature(&self) -> String {
+        r_parser::format_signature(&self.name, &self.params)
+    }
+}
+
+/// Parse the calls of a `\usage{}` section.
+///
+/// The section is R code with a few Rd macros, which are rewritten to plain R
+/// before parsing: S3 methods become their `generic.class` function, S4
+/// methods their generic, `\dots` becomes `...`. Lines that are not calls
+/// (datasets, `x$name`) are skipped.
+fn parse_usage_section(content: &str) -> Vec<Usage> {
+    let mut methods = Vec::new();
+    let code = usage_to_r(content, &mut methods);
+    let exprs = r_parser::parse(&code);
+    let text = |e: &r_parser::Expr| code[e.start..e.end].to_string();
+    let symbol = |e: &r_parser::Expr| match &e.kind {
+        ExprKind::Symbol(name) => Some(name.clone()),
+        _ => None,
+    };
+    let call = |e: &r_parser::Expr| {
+        let ExprKind::Call { function, args } = &e.kind else {
+            return None;
+        };
+        let params = args
+            .iter()
+            .filter_map(|arg| match (&arg.name, &arg.value) {
+                (Some(name), value) => Some((name.clone(), value.as_ref().map(text))),
+                (None, Some(value)) => Some((symbol(value).unwrap_or_else(|| text(value)), None)),
+                (None, None) => None,
+            })
+            .collect();
+        let name = symbol(function)?;
+        Some(match name.strip_prefix(S4_METHOD_MARK) {
+            Some(generic) => Usage {
+                name: generic.to_string(),
+                params,
+                s4_method: true,
+            },
+            None => Usage {
+                name,
+                params,
+                s4_method: false,
+            },
+        })
+    };
+
+    exprs
+        .iter()
+        .filter_map(|expr| match &expr.kind {
+            ExprKind::Call { .. } => call(expr),
+            // `names(x) <- value`
+            ExprKind::Binary { op, lhs, rhs } if op == "<-" => {
+                let mut usage = call(lhs)?;
+                // The replacement method of `names` for `tbl` is `names<-.tbl`
+                usage.name = match methods
+                    .iter()
+                    .find(|(g, c)| usage.name == format!("{g}.{c}"))
+                {
+                    Some((generic, class)) => format!("{generic}<-.{class}"),
+                    None => format!("{}<-", usage.name),
+                };
+                usage.params.push((symbol(rhs)?, None));
+                Some(usage)
+            }
+            // `x \%in\% table`, `e1 + e2`
+            ExprKind::Binary { op, lhs, rhs } => Some(Usage {
+                name: op.clone(),
+                params: vec![(symbol(lhs)?, None), (symbol(rhs)?, None)],
+                s4_method: false,
+            }),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Rewrite the Rd markup of a `\usage{}` section as R code, collecting the
+/// generic and class of S3 methods into `methods`
+fn usage_to_r(content: &str, methods: &mut Vec<(String, String)>) -> String {
+    let mut code = String::new();
+    let mut chars = content.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            // Rd comment
+            '%' => {
+                for c in chars.by_ref() {
+                    if c == '\n' {
+                        code.push(c);
+                        break;
+                    }
+                }
+            }
+            '\\' => {
+                let mut macro_name = String::new();
+                while let Some(&c) = chars.peek().filter(|c| c.is_ascii_alphanumeric()) {
+                    macro_name.push(c);
+                    chars.next();
+                }
+                let mut brace_arg = |chars: &mut std::iter::Peekable<std::str::Chars>| {
+                    if chars.peek() == Some(&'{') {
+                        chars.next();
+                        usage_to_r(&extract_brace_content_nested(chars), methods)
+                    } else {
+                        String::new()
+                    }
+                };
+                match macro_name.as_str() {
+                    // Escaped character (`\%`, `\{`)
+                    "" => code.extend(chars.next()),
+                    "dots" | "ldots" => code.push_str("..."),
+                    "method" | "S3method" => {
+                        let generic = brace_arg(&mut chars);
+                        let class = brace_arg(&mut chars);
+                        code.push_str(&format!("`{generic}.{class}`"));
+                        methods.push((generic, class));
+                    }
+                    "S4method" => {
+                        let generic = brace_arg(&mut chars);
+                        brace_arg(&mut chars);
+                        code.push_str(&format!("`{S4_METHOD_MARK}{generic}`"));
+                    }
+                    // `\special{}`, `\code{}`, ...: keep the content
+                    _ => code.push_str(&brace_arg(&mut chars)),
+                }
+            }
+            c => code.push(c),
+        }
+    }
+    code
+}
+
 /// Extract all sections from Rd content, handling nested braces properly
 fn extract_rd_sections(content: &str) -> BTreeMap<String, String> {
     let mut sections = BTreeMap::new();
@@ -208,6 +438,16 @@ fn extract_rd_sections(content: &str) -> BTreeMap<String, String> {
                     let after_prefix = trimmed.strip_prefix(&prefix).unwrap_or("");
                     section_content = after_prefix.to_string();
                     brace_depth = 1 + count_braces(after_prefix);
+                    // Section opened and closed on the same line
+                    if brace_depth <= 0 {
+                        let content = section_content
+                            .strip_suffix('}')
+                            .unwrap_or(&section_content)
+                            .to_string();
+                        sections.insert(current_section.clone(), content);
+                        section_content.clear();
+                        brace_depth = 0;
+                    }
                     break;
                 }
             }
@@ -293,10 +533,16 @@ fn parse_arguments_section(content: &str) -> BTreeMap<String, String> {
                 brace_depth -= 1;
                 if brace_depth == 0 {
                     // End of this item's description
-                    let name = current_name.trim().to_string();
+                    // `\item{x, y}` documents several arguments at once
                     let desc = sanitize(current_desc.trim());
-                    if !name.is_empty() {
-                        arguments.insert(name, desc);
+                    for name in current_name.split(',') {
+                        let name = match name.trim() {
+                            "\\dots" | "\\ldots" => "...",
+                            name => name,
+                        };
+                        if !name.is_empty() {
+                            arguments.insert(name.to_string(), desc.clone());
+                        }
                     }
                     in_item = false;
                     current_name.clear();
@@ -467,148 +713,217 @@ fn is_valid_example(block: &str) -> bool {
     !trimmed.is_empty() && trimmed != "}" && trimmed != "{" && !trimmed.starts_with("\\")
 }
 
-/// Parse R files for function definitions
-fn parse_r_files(
-    path: &Path,
-    exports: &[String],
-    rd_docs: &BTreeMap<String, RdDoc>,
-    include_internal: bool,
-) -> Result<Vec<FunctionRecord>> {
-    let mut functions = Vec::new();
+/// An R source file and its top-level expressions
+struct RSource {
+    content: String,
+    exprs: Vec<Expr>,
+}
 
+/// Read and parse the files in R/, in collation order as R sources them:
+/// the files listed in the DESCRIPTION `Collate` field, in that order, or
+/// else every R file in alphabetical order
+fn read_r_files(path: &Path, collate: Option<&str>) -> Result<Vec<RSource>> {
     let r_path = path.join("R");
     if !r_path.exists() {
-        return Ok(functions);
+        return Ok(Vec::new());
     }
 
-    for entry in fs::read_dir(&r_path)? {
-        let entry = entry?;
-        let file_path = entry.path();
+    let files: Vec<_> = match collate {
+        Some(collate) => collate_files(collate)
+            .iter()
+            .map(|name| r_path.join(name))
+            .collect(),
+        None => {
+            let mut files = Vec::new();
+            for entry in fs::read_dir(&r_path)? {
+                let file_path = entry?.path();
+                if file_path.extension().is_some_and(|e| e == "R" || e == "r") {
+                    files.push(file_path);
+                }
+            }
+            files.sort();
+            files
+        }
+    };
+
+    Ok(files
+        .iter()
+        .filter_map(|file_path| fs::read_to_string(file_path).ok())
+        .map(|content| RSource {
+            exprs: r_parser::parse(&content),
+            content,
+        })
+        .collect())
+}
 
-        if file_path.extension().is_some_and(|e| e == "R" || e == "r") {
-            if let Ok(content) = fs::read_to_string(&file_path) {
-                let file_funcs =
-                    extract_functions_from_r(&content, exports, rd_docs, include_internal);
-                functions.extend(file_funcs);
+/// File names of a `Collate` field: whitespace-separated, and quoted when
+/// they contain spaces
+fn collate_files(collate: &str) -> Vec<String> {
+    let mut files = Vec::new();
+    let mut rest = collate.trim_start();
+    while let Some(first) = rest.chars().next() {
+        let (name, remainder) = match first {
+            '\'' | '"' => {
+                let body = &rest[1..];
+                let end = body.find(first).unwrap_or(body.len());
+                (&body[..end], body.get(end + 1..).unwrap_or(""))
+            }
+            _ => {
+                let end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+                (&rest[..end], &rest[end..])
             }
+        };
+        // A file name cannot leave R/
+        if !name.is_empty() && !name.contains("..") {
+            files.push(name.to_string());
         }
+        rest = remainder.trim_start();
     }
-
-    Ok(functions)
+    files
 }
 
-/// Extract function definitions from R source code
+/// Extract the closures assigned at the top level of R source code
 fn extract_functions_from_r(
     content: &str,
-    exports: &[String],
-    rd_docs: &BTreeMap<String, RdDoc>,
+    exprs: &[Expr],
+    namespace: &Namespace,
+    rd_docs: &RdIndex,
     include_internal: bool,
 ) -> Vec<FunctionRecord> {
     let mut functions = Vec::new();
 
-    // Simple regex-like parsing for: name <- function(args) or name = function(args)
-    let lines: Vec<&str> = content.lines().collect();
-
-    for (i, line) in lines.iter().enumerate() {
-        let line = line.trim();
+    for closure in r_parser::closures(exprs) {
+        let name = closure.name.clone();
+        let exported = namespace.is_exported(&name);
 
-        // Skip comments
-        if line.starts_with('#') {
+        // Skip internal functions unless requested
+        if !include_internal && name.starts_with('.') {
+            continue;
+        }
+        if !include_internal && !exported {
             continue;
         }
 
-        // Look for function assignment patterns
-        let patterns = ["<- function(", "= function(", "<-function(", "=function("];
-
-        for pattern in patterns {
-            if let Some(pos) = line.find(pattern) {
-                let name = line[..pos].trim().to_string();
-                if name.is_empty() || name.contains(' ') {
-                    continue;
-                }
-
-                let exported = exports.contains(&name) || exports.is_empty();
-
-                // Skip internal functions unless requested
-                if !include_internal && name.starts_with('.') {
-                    continue;
-                }
-                if !include_internal && !exported {
-                    continue;
-                }
-
-                // Extract signature (may span multiple lines)
-                let mut sig_content = line[pos + pattern.len()..].to_string();
-                let mut paren_depth = 1;
-                let mut j = i;
-
-                while paren_depth > 0 && j < lines.len() {
-                    for c in sig_content.chars() {
-                        if c == '(' {
-                            paren_depth += 1;
-                        }
-                        if c == ')' {
-                            paren_depth -= 1;
-                        }
-                    }
-                    if paren_depth > 0 {
-                        j += 1;
-                        if j < lines.len() {
-                            sig_content.push_str(lines[j].trim());
-                        }
-                    }
-                }
-
-                // Build signature
-                let args_end = sig_content.rfind(')').unwrap_or(sig_content.len());
-                let args = &sig_content[..args_end];
-                let signature = format!("{}({})", name, args.trim());
-
-                // Get documentation
-                let doc = rd_docs.get(&name);
+        let params = closure.parameters(content);
+        let signature = closure.signature(content);
+
+        // Get documentation
+        let doc = rd_docs.get(&name);
+        let usage = doc.and_then(|d| d.usages.iter().find(|u| u.name == name && !u.s4_method));
+        if let Some(usage) = usage {
+            if !same_parameters(&usage.params, &params) {
+                eprintln!(
+                    "  → Warning: {} documents {} but the code defines {signature}",
+                    doc.map_or("", |d| d.file.as_str()),
+                    usage.signature(),
+                );
+            }
+        }
 
-                let mut arguments = BTreeMap::new();
-                if let Some(d) = doc {
-                    arguments = d.arguments.clone();
-                }
+        functions.push(function_record(name, exported, signature, &params, doc));
+    }
 
-                let examples: Vec<Example> = doc
-                    .map(|d| {
-                        d.examples
-                            .iter()
-                            .take(3)
-                            .map(|e| Example {
-                                code: e.clone(),
-                                shows: Vec::new(),
-                            })
-                            .collect()
-                    })
-                    .unwrap_or_default();
-
-                let func = FunctionRecord {
-                    name: name.clone(),
-                    exported,
-                    signature,
-                    purpose: doc.and_then(|d| d.title.clone()),
-                    role: None,
-                    arguments,
-                    arg_types: BTreeMap::new(),
-                    returns: doc.and_then(|d| d.value.clone()),
-                    return_type: None,
-                    constraints: Vec::new(),
-                    examples,
-                    related: Vec::new(),
-                };
+    functions
+}
 
-                functions.push(func);
-                break;
+/// Records for the exported functions shown in `\usage{}` sections that
+/// are not among the `defined` functions
+fn usage_functions(
+    rd_docs: &RdIndex,
+    namespace: &Namespace,
+    defined: &BTreeSet<String>,
+) -> Vec<FunctionRecord> {
+    let mut seen = defined.clone();
+    let mut functions = Vec::new();
+    for doc in &rd_docs.docs {
+        for usage in &doc.usages {
+            // An S4 method is not a function of its own: its usage only shows
+            // the arguments of one class
+            if usage.s4_method
+                || !namespace.is_exported(&usage.name)
+                || !seen.insert(usage.name.clone())
+            {
+                continue;
             }
+            functions.push(function_record(
+                usage.name.clone(),
+                true,
+                usage.signature(),
+                &usage.params,
+                Some(doc),
+            ));
         }
     }
-
     functions
 }
 
+/// Whether documented and defined parameters agree in names, order and
+/// defaults (ignoring whitespace, which usage sections reflow)
+fn same_parameters(a: &[(String, Option<String>)], b: &[(String, Option<String>)]) -> bool {
+    let normalize = |params: &[(String, Option<String>)]| -> Vec<(String, Option<String>)> {
+        params
+            .iter()
+            .map(|(name, default)| {
+                let default = default
+                    .as_ref()
+                    .map(|d| d.chars().filter(|c| !c.is_whitespace()).collect());
+                (name.clone(), default)
+            })
+            .collect()
+    };
+    normalize(a) == normalize(b)
+}
+
+/// Build the record of a function with the given parameters, documented by
+/// `doc` (which may cover other functions too)
+fn function_record(
+    name: String,
+    exported: bool,
+    signature: String,
+    params: &[(String, Option<String>)],
+    doc: Option<&RdDoc>,
+) -> FunctionRecord {
+    // A shared topic documents the arguments of all its functions
+    let arguments: BTreeMap<String, String> = doc
+        .map(|d| {
+            d.arguments
+                .iter()
+                .filter(|(arg, _)| params.iter().any(|(p, _)| p == *arg))
+                .map(|(arg, desc)| (arg.clone(), desc.clone()))
+                .collect()
+        })
+        .unwrap_or_default();
+
+    let examples: Vec<Example> = doc
+        .map(|d| {
+            d.examples
+                .iter()
+                .take(3)
+                .map(|e| Example {
+                    code: e.clone(),
+                    shows: Vec::new(),
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+
+    FunctionRecord {
+        name,
+        package: None,
+        exported,
+        signature,
+        purpose: doc.and_then(|d| d.title.clone()),
+        role: None,
+        arguments,
+        arg_types: BTreeMap::new(),
+        returns: doc.and_then(|d| d.value.clone()),
+        return_type: None,
+        constraints: Vec::new(),
+        examples,
+        related: Vec::new(),
+    }
+}
+
 /// Remove Rd markup and normalize whitespace
 fn sanitize(s: &str) -> String {
     let stripped = strip_rd_markup(s);
@@ -791,3 +1106,197 @@ fn extract_brace_content_nested(chars: &mut std::iter::Peekable<std::str::Chars>
 
     content
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shared_rd_topics_document_each_function() {
+        let dir = tempfile::TempDir::new().unwrap();
+        fs::create_dir(dir.path().join("man")).unwrap();
+        fs::write(
+            dir.path().join("man/mutate-joins.Rd"),
+            r"\name{mutate-joins}
+\alias{mutate-joins}
+\alias{inner_join}
+\alias{left_join}
+\alias{\%join\%}
+\title{Mutating joins}
+\arguments{
+\item{x, y}{A pair of data frames.}
+
+\item{keep}{Keep the join keys?}
+
+\item{\dots}{Other parameters.}
+}
+",
+        )
+        .unwrap();
+
+        let rd_docs = parse_rd_files(dir.path()).unwrap();
+        let code = "inner_join <- function(x, y, ...) NULL\n\
+                    left_join <- function(x, y, keep = NULL) NULL\n\
+                    `%join%` <- function(x, y) inner_join(x, y)\n";
+        let functions = extract_functions_from_r(
+            code,
+            &r_parser::parse(code),
+            &Namespace::parse(""),
+            &rd_docs,
+            true,
+        );
+
+        let documented: Vec<(&str, Option<&str>, Vec<&str>)> = functions
+            .iter()
+            .map(|f| {
+                (
+                    f.name.as_str(),
+                    f.purpose.as_deref(),
+                    f.arguments.keys().map(String::as_str).collect(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            documented,
+            [
+                ("inner_join", Some("Mutating joins"), vec!["...", "x", "y"]),
+                ("left_join", Some("Mutating joins"), vec!["keep", "x", "y"]),
+                ("%join%", Some("Mutating joins"), vec!["x", "y"]),
+            ]
+        );
+        assert_eq!(functions[0].arguments["y"], "A pair of data frames.");
+    }
+
+    #[test]
+    fn test_parse_usage_section() {
+        let usages = parse_usage_section(
+            r#"tibble(
+  ...,
+  .rows = NULL,
+  .name_repair = c("check_unique", "unique")
+)
+
+\method{print}{tbl_df}(x, \dots, n = NULL) % Rd comment (
+\S4method{show}{Tbl}(object)
+## S3 replacement method for class 'tbl'
+\method{names}{tbl}(x) <- value
+lhs \%>\% rhs
+mtcars"#,
+        );
+        let signatures: Vec<String> = usages.iter().map(Usage::signature).collect();
+        assert_eq!(
+            signatures,
+            [
+                r#"tibble(..., .rows = NULL, .name_repair = c("check_unique", "unique"))"#,
+                "print.tbl_df(x, ..., n = NULL)",
+                "show(object)",
+                "`names<-.tbl`(x, value)",
+                "`%>%`(lhs, rhs)",
+            ]
+        );
+        assert!(usages[2].s4_method && !usages[1].s4_method);
+    }
+
+    #[test]
+    fn test_s4_method_usages_are_not_functions() {
+        let dir = tempfile::TempDir::new().unwrap();
+        fs::create_dir(dir.path().join("man")).unwrap();
+        fs::write(
+            dir.path().join("man/Tbl-class.Rd"),
+            r"\name{Tbl-class}
+\alias{show,Tbl-method}
+\title{Tables}
+\usage{
+\S4method{show}{Tbl}(object)
+}
+",
+        )
+        .unwrap();
+
+        let rd_docs = parse_rd_files(dir.path()).unwrap();
+        let namespace = Namespace::parse("exportMethods(show)\n");
+        let documented = usage_functions(&rd_docs, &namespace, &BTreeSet::new());
+        assert!(documented.is_empty());
+    }
+
+    #[test]
+    fn test_r_files_follow_collate_order() {
+        let dir = tempfile::TempDir::new().unwrap();
+        fs::create_dir(dir.path().join("R")).unwrap();
+        for name in ["aaa.R", "zzz.R", "with space.R", "unlisted.R"] {
+            fs::write(dir.path().join("R").join(name), format!("# {name}\n")).unwrap();
+        }
+
+        let read = |collate: Option<&str>| -> Vec<String> {
+            read_r_files(dir.path(), collate)
+                .unwrap()
+                .into_iter()
+                .map(|source| source.content.trim().to_string())
+                .collect()
+        };
+        assert_eq!(
+            read(Some("zzz.R\n    'with space.R' aaa.R")),
+            ["# zzz.R", "# with space.R", "# aaa.R"]
+        );
+        assert_eq!(
+            read(None),
+            ["# aaa.R", "# unlisted.R", "# with space.R", "# zzz.R"]
+        );
+    }
+
+    #[test]
+    fn test_usage_documents_undetected_functions() {
+        let dir = tempfile::TempDir::new().unwrap();
+        fs::create_dir(dir.path().join("man")).unwrap();
+        fs::write(
+            dir.path().join("man/compose.Rd"),
+            r"\name{compose}
+\alias{compose}
+\alias{wrap}
+\title{Function composition}
+\usage{
+compose(f, g)
+
+wrap(x,
+  sep = ', ')
+
+internal_helper()
+}
+\arguments{
+\item{f, g}{Functions.}
+
+\item{x}{Object to wrap.}
+}
+",
+        )
+        .unwrap();
+
+        let rd_docs = parse_rd_files(dir.path()).unwrap();
+        let namespace = Namespace::parse("export(compose, wrap)\n");
+        let code = "wrap <- function(x, sep = ', ') x\ncompose <- make_composer()\n";
+        let functions =
+            extract_functions_from_r(code, &r_parser::parse(code), &namespace, &rd_docs, false);
+        assert_eq!(functions.len(), 1);
+        assert!(same_parameters(
+            &rd_docs.get("wrap").unwrap().usages[1].params,
+            &[
+                ("x".to_string(), None),
+                ("sep".to_string(), Some("', '".to_string()))
+            ],
+        ));
+
+        let defined = functions.iter().map(|f| f.name.clone()).collect();
+        let documented = usage_functions(&rd_docs, &namespace, &defined);
+        assert_eq!(documented.len(), 1);
+        assert_eq!(documented[0].name, "compose");
+        assert_eq!(documented[0].signature, "compose(f, g)");
+        assert_eq!(
+            documented[0].purpose.as_deref(),
+            Some("Function composition")
+        );
+        assert_eq!(
+            documented[0].arguments.keys().collect::<Vec<_>>(),
+            ["f", "g"]
+        );
+    }
+}
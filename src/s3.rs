@@ -0,0 +1,356 @@
+//! S3 generics and methods
+//!
+//! R dispatches `print(x)` to `print.tbl_df()` when `x` has class `tbl_df`.
+//! Methods are reached through their generic rather than called directly, so
+//! they are described under a `generic` record instead of as standalone
+//! functions. A package's own generics are the closures that call
+//! `UseMethod()`. Its methods are the NAMESPACE `S3method()` registrations,
+//! the `s3_register()` calls that register methods for generics of suggested
+//! packages at load time, and the `generic.class` functions of its own
+//! generics, which dispatch finds without registration. An unregistered
+//! `generic.class` function only counts as a method when it is not exported
+//! under its own name and takes the generic's first argument, so that
+//! helpers which merely share a prefix stay functions.
+
+use crate::namespace::S3Method;
+use crate::r_parser::{self, Expr, ExprKind};
+use crate::schema::{FunctionRecord, GenericRecord, MethodRecord, Record};
+use std::collections::{BTreeSet, HashMap};
+
+/// Names of the top-level closures of parsed R code that dispatch with
+/// `UseMethod()`
+pub fn generics(exprs: &[Expr]) -> Vec<String> {
+    r_parser::closures(exprs)
+        .into_iter()
+        .filter(|closure| {
+            let mut dispatches = false;
+            closure.body.walk(&mut |e| {
+                dispatches |= e.call_name() == Some("UseMethod");
+            });
+            dispatches
+        })
+        .map(|closure| closure.name)
+        .collect()
+}
+
+/// First parameter of each top-level closure of parsed R code, which is
+/// the object a generic dispatches on
+pub fn first_parameters(exprs: &[Expr]) -> HashMap<String, String> {
+    r_parser::closures(exprs)
+        .into_iter()
+        .filter_map(|closure| Some((closure.name, closure.params.first()?.name.clone())))
+        .collect()
+}
+
+/// Methods registered with `s3_register(generic, class, method)` anywhere
+/// in parsed R code (usually in `.onLoad()`), from vctrs or the copy many
+/// packages vendor
+pub fn registrations(exprs: &[Expr]) -> Vec<S3Method> {
+    let mut methods = Vec::new();
+    for expr in exprs {
+        expr.walk(&mut |e| {
+            if e.call_name() != Some("s3_register") {
+                return;
+            }
+            let ExprKind::Call { args, .. } = &e.kind else {
+                return;
+            };
+            let argument = |name: &str, position: usize| {
+                args.iter()
+                    .find(|a| a.name.as_deref() == Some(name))
+                    .or_else(|| args.iter().filter(|a| a.name.is_none()).nth(position))
+                    .and_then(|a| a.value.as_ref())
+                    .map(|v| &v.kind)
+            };
+            let (Some(ExprKind::Str(generic)), Some(ExprKind::Str(class))) =
+                (argument("generic", 0), argument("class", 1))
+            else {
+                return;
+            };
+            // The generic is named with its package: "pillar::pillar_shaft"
+            let generic = generic.rsplit("::").next().unwrap_or(generic).to_string();
+            let function = match argument("method", 2) {
+                Some(ExprKind::Symbol(function)) => function.clone(),
+                _ => format!("{generic}.{class}"),
+            };
+            methods.push(S3Method {
+                generic,
+                class: class.clone(),
+                function,
+            });
+        });
+    }
+    methods
+}
+
+/// Turn the package's function records into records of functions and
+/// generics, moving methods under their generic.
+///
+/// `generics` are the package's own generics (exported or not),
+/// `registered` its method registrations and `first_parameters` the first
+/// parameter of each closure the package defines. Generics of the package appear in
+/// place of their function record, generics of other packages after all
+/// functions. Generics of the package without a function record (internal
+/// ones, when those are left out) are dropped with their methods.
+pub fn group(
+    functions: Vec<FunctionRecord>,
+    generics: &[String],
+    registered: &[S3Method],
+    first_parameters: &HashMap<String, String>,
+) -> Vec<Record> {
+    // Unregistered methods of the package's own generics
+    let mut methods: Vec<S3Method> = registered.to_vec();
+    for generic in generics {
+        let dispatched = first_parameters.get(generic);
+        for func in &functions {
+            let Some(class) = func.name.strip_prefix(&format!("{generic}.")) else {
+                continue;
+            };
+            let takes_dispatched = match dispatched {
+                Some(arg) => first_parameters.get(&func.name) == Some(arg),
+                None => true,
+            };
+            let is_method = !class.is_empty() && !func.exported && takes_dispatched;
+            if is_method && !methods.iter().any(|m| m.function == func.name) {
+                methods.push(S3Method {
+                    generic: generic.clone(),
+                    class: class.to_string(),
+                    function: func.name.clone(),
+                });
+            }
+        }
+    }
+    let mut seen = BTreeSet::new();
+    methods.retain(|m| seen.insert((m.generic.clone(), m.class.clone())));
+
+    let by_name: HashMap<&str, &FunctionRecord> =
+        functions.iter().map(|f| (f.name.as_str(), f)).collect();
+    let method_records = |generic: &str, generic_arguments: &[&String]| -> Vec<MethodRecord> {
+        methods
+            .iter()
+            .filter(|m| m.generic == generic)
+            .map(|m| {
+                let func = by_name.get(m.function.as_str());
+                MethodRecord {
+                    class: m.class.clone(),
+                    function: m.function.clone(),
+                    signature: func.map(|f| f.signature.clone()),
+                    purpose: func.and_then(|f| f.purpose.clone()),
+                    // Arguments the generic does not document already
+                    arguments: func
+                        .map(|f| {
+                            f.arguments
+                                .iter()
+                                .filter(|(arg, _)| !generic_arguments.contains(arg))
+                                .map(|(arg, desc)| (arg.clone(), desc.clone()))
+                                .collect()
+                        })
+                        .unwrap_or_default(),
+                }
+            })
+            .collect()
+    };
+
+    let method_functions: BTreeSet<&str> = methods
+        .iter()
+        .filter(|m| !generics.contains(&m.function))
+        .map(|m| m.function.as_str())
+        .collect();
+    let mut records = Vec::new();
+    for func in &functions {
+        if generics.contains(&func.name) {
+            let generic_arguments: Vec<&String> = func.arguments.keys().collect();
+            records.push(Record::Generic(GenericRecord {
+                name: func.name.clone(),
+                package: func.package.clone(),
+                exported: func.exported,
+                external: false,
+                signature: Some(func.signature.clone()),
+                purpose: func.purpose.clone(),
+                arguments: func.arguments.clone(),
+                returns: func.returns.clone(),
+                examples: func.examples.clone(),
+                methods: method_records(&func.name, &generic_arguments),
+            }));
+        } else if !method_functions.contains(func.name.as_str()) {
+            records.push(Record::Function(func.clone()));
+        }
+    }
+
+    let mut external = BTreeSet::new();
+    for method in &methods {
+        if generics.contains(&method.generic) || !external.insert(method.generic.as_str()) {
+            continue;
+        }
+        records.push(Record::Generic(GenericRecord {
+            name: method.generic.clone(),
+            package: None,
+            exported: true,
+            external: true,
+            signature: None,
+            purpose: None,
+            arguments: Default::default(),
+            returns: None,
+            examples: Vec::new(),
+            methods: method_records(&method.generic, &[]),
+        }));
+    }
+
+    records
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+
+    fn function(name: &str, signature: &str, arguments: &[&str]) -> FunctionRecord {
+        FunctionRecord {
+            name: name.to_string(),
+            package: None,
+            exported: true,
+            signature: signature.to_string(),
+            purpose: None,
+            role: None,
+            arguments: arguments
+                .iter()
+                .map(|a| (a.to_string(), format!("The {a}.")))
+                .collect::<BTreeMap<_, _>>(),
+            arg_types: BTreeMap::new(),
+            returns: None,
+            return_type: None,
+            constraints: Vec::new(),
+            examples: Vec::new(),
+            related: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_methods_appear_under_their_generic() {
+        let code = r#"
+tidy_up <- function(x, ...) {
+  UseMethod("tidy_up")
+}
+tidy_up.default <- function(x, ...) x
+tidy_up.tbl <- function(x, ..., keep = TRUE) x
+print.tidied <- function(x, ...) invisible(x)
+helper <- function() NULL
+.onLoad <- function(libname, pkgname) {
+  vctrs::s3_register("pillar::pillar_shaft", "tidied")
+  s3_register("knitr::knit_print", class = "tidied", method = knit_tidied)
+}
+"#;
+        let exprs = r_parser::parse(code);
+        let generics = generics(&exprs);
+        assert_eq!(generics, ["tidy_up"]);
+
+        let mut registered =
+            crate::namespace::Namespace::parse("S3method(print, tidied)\nS3method(tidy_up, tbl)\n")
+                .s3_methods;
+        registered.extend(registrations(&exprs));
+
+        let functions = vec![
+            function("tidy_up", "tidy_up(x, ...)", &["x", "..."]),
+            FunctionRecord {
+                exported: false,
+                ..function("tidy_up.default", "tidy_up.default(x, ...)", &[])
+            },
+            function(
+                "tidy_up.tbl",
+                "tidy_up.tbl(x, ..., keep = TRUE)",
+                &["x", "keep"],
+            ),
+            function("print.tidied", "print.tidied(x, ...)", &[]),
+            function("helper", "helper()", &[]),
+        ];
+        let records = group(functions, &generics, &registered, &first_parameters(&exprs));
+
+        let summary: Vec<String> = records
+            .iter()
+            .map(|record| match record {
+                Record::Function(f) => f.name.clone(),
+                Record::Generic(g) => {
+                    let methods: Vec<String> = g
+                        .methods
+                        .iter()
+                        .map(|m| format!("{}={}", m.class, m.function))
+                        .collect();
+                    format!(
+                        "{}{}[{}]",
+                        g.name,
+                        if g.external { "*" } else { "" },
+                        methods.join(" ")
+                    )
+                }
+                _ => unreachable!(),
+            })
+            .collect();
+        assert_eq!(
+            summary,
+            [
+                "tidy_up[tbl=tidy_up.tbl default=tidy_up.default]",
+                "helper",
+                "print*[tidied=print.tidied]",
+                "pillar_shaft*[tidied=pillar_shaft.tidied]",
+                "knit_print*[tidied=knit_tidied]",
+            ]
+        );
+
+        let Record::Generic(tidy_up) = &records[0] else {
+            unreachable!()
+        };
+        assert_eq!(tidy_up.signature.as_deref(), Some("tidy_up(x, ...)"));
+        let tbl = &tidy_up.methods[0];
+        assert_eq!(
+            tbl.signature.as_deref(),
+            Some("tidy_up.tbl(x, ..., keep = TRUE)")
+        );
+        assert_eq!(tbl.arguments.keys().collect::<Vec<_>>(), ["keep"]);
+    }
+
+    #[test]
+    fn test_unregistered_lookalikes_stay_functions() {
+        let functions = vec![
+            function("tidy_up", "tidy_up(x, ...)", &["x", "..."]),
+            // Exported under its own name: a function of its own
+            function("tidy_up.all", "tidy_up.all(x, ...)", &[]),
+            // Does not take the object dispatched on
+            FunctionRecord {
+                exported: false,
+                ..function(
+                    "tidy_up.internal_helper",
+                    "tidy_up.internal_helper(data, verbose = FALSE)",
+                    &[],
+                )
+            },
+            FunctionRecord {
+                exported: false,
+                ..function("tidy_up.list", "tidy_up.list(x, ...)", &[])
+            },
+        ];
+        let code = "
+tidy_up <- function(x, ...) UseMethod(\"tidy_up\")
+tidy_up.all <- function(x, ...) NULL
+tidy_up.internal_helper <- function(data, verbose = FALSE) NULL
+tidy_up.list <- function(x, ...) NULL
+";
+        let first_parameters = first_parameters(&r_parser::parse(code));
+        assert_eq!(first_parameters["tidy_up.internal_helper"], "data");
+        let records = group(functions, &["tidy_up".to_string()], &[], &first_parameters);
+
+        let names: Vec<&str> = records
+            .iter()
+            .map(|record| match record {
+                Record::Function(f) => f.name.as_str(),
+                Record::Generic(g) => g.name.as_str(),
+                _ => unreachable!(),
+            })
+            .collect();
+        assert_eq!(names, ["tidy_up", "tidy_up.all", "tidy_up.internal_helper"]);
+        let Record::Generic(tidy_up) = &records[0] else {
+            unreachable!()
+        };
+        let classes: Vec<&str> = tidy_up.methods.iter().map(|m| m.class.as_str()).collect();
+        assert_eq!(classes, ["list"]);
+    }
+}
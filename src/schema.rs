@@ -1,6 +1,6 @@
 //! YAML output schema types for pkgctx
 //!
-//! Defines the record types that match the v1.1 schema specification.
+//! Defines the record types that match the v1.3 schema specification.
 
 use serde::{Deserialize, Serialize};
 use std::collections::BTreeMap;
@@ -9,12 +9,16 @@ use std::collections::BTreeMap;
 /// Each record is self-describing via its `kind` field.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(tag = "kind", rename_all = "lowercase")]
+// A stream holds a package record or two among many function records, so the
+// size of the package variant does not matter
+#[allow(clippy::large_enum_variant)]
 pub enum Record {
     /// Context header with LLM instructions (always first when enabled)
     #[serde(rename = "context_header")]
     ContextHeader(ContextHeaderRecord),
     Package(PackageRecord),
     Function(FunctionRecord),
+    Generic(GenericRecord),
     Class(ClassRecord),
     Workflow(WorkflowRecord),
 }
@@ -41,10 +45,73 @@ pub struct PackageRecord {
     /// Source language (R or Python)
     pub language: String,
 
+    /// Kind of distribution the source was read from ("sdist", "wheel",
+    /// "platform-wheel"), when it was not a source tree
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub artifact: Option<String>,
+
+    /// Upstream commit SHA the source was built from (git sources, r-universe)
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub commit: Option<String>,
+
+    /// Bioconductor release the package was taken from (e.g. "3.18")
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub bioconductor_release: Option<String>,
+
     /// Brief description optimized for LLM context
     #[serde(skip_serializing_if = "Option::is_none")]
     pub description: Option<String>,
 
+    /// License, as declared by the package
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub license: Option<String>,
+
+    /// Homepage and repository URLs
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub url: Vec<String>,
+
+    /// Where to report bugs
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub bug_reports: Option<String>,
+
+    /// Packages attached with this one, and the minimum R version
+    /// (e.g. "R (>= 4.1.0)", "methods")
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub depends: Vec<String>,
+
+    /// Packages whose namespaces are loaded, with version constraints
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub imports: Vec<String>,
+
+    /// Packages needed only for examples, tests or optional features
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub suggests: Vec<String>,
+
+    /// System libraries and tools needed to install the package
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub system_requirements: Option<String>,
+
+    /// Character encoding of the package sources and documentation
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub encoding: Option<String>,
+
+    /// Names imported by the NAMESPACE, by package (an empty list imports
+    /// the whole namespace)
+    #[serde(skip_serializing_if = "BTreeMap::is_empty", default)]
+    pub namespace_imports: BTreeMap<String, Vec<String>>,
+
+    /// Supported Python versions (e.g. ">=3.9")
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub requires_python: Option<String>,
+
+    /// Requirements installed with a Python package (PEP 508)
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub dependencies: Vec<String>,
+
+    /// Trove classifiers of a Python package
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub classifiers: Vec<String>,
+
     /// Hints for LLM on how to use this package
     #[serde(skip_serializing_if = "Vec::is_empty", default)]
     pub llm_hints: Vec<String>,
@@ -52,6 +119,29 @@ pub struct PackageRecord {
     /// Common arguments shared across many functions
     #[serde(skip_serializing_if = "BTreeMap::is_empty", default)]
     pub common_arguments: BTreeMap<String, String>,
+
+    /// Where the package source came from and which bytes were read
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub provenance: Option<Provenance>,
+}
+
+/// Traceability information for a package record
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Provenance {
+    /// URL the source was downloaded from
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub source_url: Option<String>,
+
+    /// SHA-256 of the downloaded artifact
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub sha256: Option<String>,
+
+    /// How the source was obtained (e.g. "cran", "pypi", "github", "local")
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub fetch_method: Option<String>,
+
+    /// Version of pkgctx that produced the record
+    pub pkgctx_version: String,
 }
 
 /// Function/method record
@@ -60,6 +150,10 @@ pub struct FunctionRecord {
     /// Function name
     pub name: String,
 
+    /// Package the function belongs to (set when dependencies are included)
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub package: Option<String>,
+
     /// Whether this function is exported (public API)
     pub exported: bool,
 
@@ -114,12 +208,82 @@ pub struct Example {
     pub shows: Vec<String>,
 }
 
+/// S3 generic record: a function dispatching on the class of its argument,
+/// with the methods implementing it for each class
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GenericRecord {
+    /// Generic name
+    pub name: String,
+
+    /// Package the generic belongs to (set when dependencies are included)
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub package: Option<String>,
+
+    /// Whether this generic is exported (public API)
+    pub exported: bool,
+
+    /// Whether the generic comes from another package (e.g. `print` from
+    /// base), so that only its methods are described
+    #[serde(skip_serializing_if = "std::ops::Not::not", default)]
+    pub external: bool,
+
+    /// Full signature of the generic (absent for external generics)
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub signature: Option<String>,
+
+    /// One-line description of what the generic does
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub purpose: Option<String>,
+
+    /// Argument descriptions (name -> description)
+    #[serde(skip_serializing_if = "BTreeMap::is_empty", default)]
+    pub arguments: BTreeMap<String, String>,
+
+    /// Description of return value
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub returns: Option<String>,
+
+    /// Usage examples
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub examples: Vec<Example>,
+
+    /// Methods of the generic, one per class
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub methods: Vec<MethodRecord>,
+}
+
+/// An S3 method, listed under its generic
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MethodRecord {
+    /// Class the method handles
+    pub class: String,
+
+    /// Function implementing the method (usually `generic.class`)
+    pub function: String,
+
+    /// Full signature of the implementing function, when it was found
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub signature: Option<String>,
+
+    /// One-line description of the method
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub purpose: Option<String>,
+
+    /// Descriptions of the method's own arguments
+    #[serde(skip_serializing_if = "BTreeMap::is_empty", default)]
+    pub arguments: BTreeMap<String, String>,
+}
+
 /// Class record for OOP constructs
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ClassRecord {
     /// Class name
     pub name: String,
 
+    /// Package the class belongs to (set when dependencies are included)
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub package: Option<String>,
+
     /// Functions that construct instances of this class
     #[serde(skip_serializing_if = "Vec::is_empty", default)]
     pub constructed_by: Vec<String>,
@@ -144,4 +308,4 @@ pub struct WorkflowRecord {
 }
 
 /// Current schema version
-pub const SCHEMA_VERSION: &str = "1.1";
+pub const SCHEMA_VERSION: &str = "1.3";